@@ -0,0 +1,228 @@
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Formatter};
+use std::iter::FromIterator;
+use std::ops::{
+	BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Deref, DerefMut, Not,
+};
+use super::{BitSlice, BitVector, WORD_BITS};
+
+// The number of words a BitArray<BITS, _> needs.
+// Stable Rust cannot compute an array length from BITS,
+// so it is passed as a second parameter: BitArray<100, { words_for_bits(100) }>.
+pub const fn words_for_bits(bits: usize) -> usize {
+	bits.div_ceil(WORD_BITS)
+}
+
+// A fixed-length bit vector stored inline.
+// The bits past BITS in the last word are always 0.
+// Ideally this would be BitArray<BITS> alone, but see words_for_bits();
+// new() fails to compile if WORDS is wrong, so it cannot be misused.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitArray<const BITS: usize, const WORDS: usize> {
+	words: [usize; WORDS],
+}
+
+impl<const BITS: usize, const WORDS: usize> BitArray<BITS, WORDS> {
+	const VALID_WORDS: () =
+		assert!(WORDS == words_for_bits(BITS), "WORDS must be words_for_bits(BITS)");
+
+	pub const fn new() -> Self {
+		#[allow(clippy::let_unit_value)]
+		let () = Self::VALID_WORDS;
+		BitArray { words: [0; WORDS] }
+	}
+	pub fn filled(value: bool) -> Self {
+		let mut result = Self::new();
+		result.fill(value);
+		result
+	}
+	// Returns None unless `bits` has exactly BITS bits
+	pub fn from_bits(bits: &BitSlice) -> Option<Self> {
+		if bits.len() != BITS { return None }
+
+		let mut result = Self::new();
+		result.store_bits(0, bits);
+		Some(result)
+	}
+	pub fn as_words(&self) -> &[usize; WORDS] {
+		&self.words
+	}
+	pub const fn len(&self) -> usize {
+		BITS
+	}
+	pub const fn is_empty(&self) -> bool {
+		BITS == 0
+	}
+	pub fn get(&self, index: usize) -> Option<bool> {
+		if index >= BITS { return None }
+
+		Some(self.words[index / WORD_BITS] >> (index % WORD_BITS) & 1 != 0)
+	}
+	pub fn set(&mut self, index: usize, value: bool) -> Option<()> {
+		if index >= BITS { return None }
+
+		let word = &mut self.words[index / WORD_BITS];
+		let mask = 1 << (index % WORD_BITS);
+		if value { *word |= mask } else { *word &= !mask }
+		Some(())
+	}
+	pub fn fill(&mut self, value: bool) {
+		self.words = [if value { !0 } else { 0 }; WORDS];
+		self.clear_unused_bits();
+	}
+	pub fn clear(&mut self) {
+		self.fill(false)
+	}
+
+	fn clear_unused_bits(&mut self) {
+		if let Some(last) = self.words.last_mut() {
+			let used_bits = BITS - (WORDS - 1) * WORD_BITS;
+			if used_bits < WORD_BITS { *last &= (1 << used_bits) - 1 }
+		}
+	}
+}
+
+impl<const BITS: usize, const WORDS: usize> Default for BitArray<BITS, WORDS> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<const BITS: usize, const WORDS: usize> Deref for BitArray<BITS, WORDS> {
+	type Target = BitSlice;
+
+	fn deref(&self) -> &BitSlice {
+		unsafe { BitSlice::from_raw_parts(self.words.as_ptr(), 0, BITS) }
+	}
+}
+impl<const BITS: usize, const WORDS: usize> DerefMut for BitArray<BITS, WORDS> {
+	fn deref_mut(&mut self) -> &mut BitSlice {
+		unsafe { BitSlice::from_raw_parts_mut(self.words.as_mut_ptr(), 0, BITS) }
+	}
+}
+
+impl<const BITS: usize, const WORDS: usize> Debug for BitArray<BITS, WORDS> {
+	fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+		(**self).fmt(fmt)
+	}
+}
+
+impl<const BITS: usize, const WORDS: usize> From<BitArray<BITS, WORDS>> for BitVector {
+	fn from(bits: BitArray<BITS, WORDS>) -> Self {
+		bits.to_bit_vector()
+	}
+}
+impl<const BITS: usize, const WORDS: usize> TryFrom<&BitVector> for BitArray<BITS, WORDS> {
+	type Error = &'static str;
+
+	fn try_from(bits: &BitVector) -> Result<Self, Self::Error> {
+		Self::from_bits(bits).ok_or("Length must be BITS")
+	}
+}
+
+// Missing bits are 0 and extra bits are ignored
+impl<const BITS: usize, const WORDS: usize> FromIterator<bool> for BitArray<BITS, WORDS> {
+	fn from_iter<I: IntoIterator<Item=bool>>(iter: I) -> Self {
+		let mut result = Self::new();
+		for (index, value) in iter.into_iter().take(BITS).enumerate() {
+			result.set(index, value);
+		}
+		result
+	}
+}
+
+impl<const BITS: usize, const WORDS: usize> Not for BitArray<BITS, WORDS> {
+	type Output = Self;
+
+	fn not(mut self) -> Self {
+		for word in &mut self.words { *word = !*word }
+		self.clear_unused_bits();
+		self
+	}
+}
+
+macro_rules! bitwise_op {
+	($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident, $op:tt) => {
+		impl<const BITS: usize, const WORDS: usize> $assign_trait for BitArray<BITS, WORDS> {
+			fn $assign_method(&mut self, other: Self) {
+				for (word, other_word) in self.words.iter_mut().zip(other.words.iter()) {
+					*word = *word $op *other_word;
+				}
+			}
+		}
+		impl<const BITS: usize, const WORDS: usize> $trait for BitArray<BITS, WORDS> {
+			type Output = Self;
+
+			fn $method(mut self, other: Self) -> Self {
+				self.$assign_method(other);
+				self
+			}
+		}
+	};
+}
+
+bitwise_op!(BitAnd, bitand, BitAndAssign, bitand_assign, &);
+bitwise_op!(BitOr, bitor, BitOrAssign, bitor_assign, |);
+bitwise_op!(BitXor, bitxor, BitXorAssign, bitxor_assign, ^);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	type Flags = BitArray<100, { words_for_bits(100) }>;
+
+	fn pattern(seed: usize) -> Flags {
+		(0..100).map(|i| (i * i + seed * i) % 7 < 3).collect()
+	}
+
+	#[test]
+	fn test_get_set() {
+		let mut bits = Flags::new();
+		assert_eq!(bits.len(), 100);
+		assert_eq!(bits.count_ones(), 0);
+		for i in (0..100).step_by(3) { bits.set(i, true).unwrap() }
+		assert_eq!(bits.set(100, true), None);
+		for i in 0..100 { assert_eq!(bits.get(i), Some(i % 3 == 0)) }
+		assert_eq!(bits.get(100), None);
+		assert!(bits.iter_ones().eq((0..100).step_by(3)));
+		assert_eq!(bits[90..].count_ones(), 4);
+
+		let copy = bits;
+		bits.set(0, false);
+		assert_eq!(copy.get(0), Some(true));
+		assert_eq!(Flags::filled(true).count_ones(), 100);
+		bits.clear();
+		assert_eq!(bits, Flags::default());
+	}
+
+	#[test]
+	fn test_ops() {
+		let (a, b) = (pattern(1), pattern(4));
+		let (vector_a, vector_b) = (BitVector::from(a), BitVector::from(b));
+		assert_eq!(BitVector::from(a & b), &vector_a & &vector_b);
+		assert_eq!(BitVector::from(a | b), &vector_a | &vector_b);
+		assert_eq!(BitVector::from(a ^ b), &vector_a ^ &vector_b);
+		assert_eq!(BitVector::from(!a), !&vector_a);
+		// Negation must not set the bits past the end
+		assert_eq!(!Flags::new(), Flags::filled(true));
+		assert_eq!((!a).count_ones(), 100 - a.count_ones());
+		let mut c = a;
+		c ^= a;
+		assert_eq!(c, Flags::new());
+	}
+
+	#[test]
+	fn test_conversions() {
+		let bits = pattern(2);
+		let vector = BitVector::from(bits);
+		assert_eq!(vector.len(), 100);
+		assert!(vector.iter().eq(bits.iter()));
+		assert_eq!(Flags::from_bits(&vector), Some(bits));
+		assert_eq!(Flags::from_bits(&vector[1..]), None);
+		assert_eq!(Flags::try_from(&vector), Ok(bits));
+		assert_eq!(Flags::try_from(&BitVector::from_iter(vec![true; 99])), Err("Length must be BITS"));
+		let shifted = BitArray::<64, 1>::from_bits(&vector[3..67]).unwrap();
+		assert!(shifted.iter().eq(vector[3..67].iter()));
+		assert_eq!(format!("{:?}", BitArray::<3, 1>::from_iter(vec![true, false, true])), "[1, 0, 1]");
+	}
+}
@@ -0,0 +1,150 @@
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use super::{BitVector, WORD_BITS};
+
+// A fixed-length bit vector whose bits can be set and cleared through a shared reference
+pub struct AtomicBitVector {
+	len: usize,
+	words: Vec<AtomicUsize>,
+}
+
+impl AtomicBitVector {
+	pub fn new(len: usize) -> Self {
+		let words = (0..len.div_ceil(WORD_BITS)).map(|_| AtomicUsize::new(0)).collect();
+		AtomicBitVector { len, words }
+	}
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	pub fn word_count(&self) -> usize {
+		self.words.len()
+	}
+
+	pub fn load(&self, index: usize, order: Ordering) -> Option<bool> {
+		let (word, mask) = self.locate(index)?;
+		Some(word.load(order) & mask != 0)
+	}
+	// Sets the bit and returns its previous value
+	pub fn test_and_set(&self, index: usize, order: Ordering) -> Option<bool> {
+		let (word, mask) = self.locate(index)?;
+		Some(word.fetch_or(mask, order) & mask != 0)
+	}
+	// Clears the bit and returns its previous value
+	pub fn clear(&self, index: usize, order: Ordering) -> Option<bool> {
+		let (word, mask) = self.locate(index)?;
+		Some(word.fetch_and(!mask, order) & mask != 0)
+	}
+	// Bits [word_index * WORD_BITS, (word_index + 1) * WORD_BITS), with bit 0 as the lowest
+	pub fn load_word(&self, word_index: usize, order: Ordering) -> Option<usize> {
+		Some(self.words.get(word_index)?.load(order))
+	}
+	// Sets all the bits of `mask` in one word and returns the word's previous value.
+	// Bits of `mask` past the end of the vector are ignored.
+	pub fn fetch_or_word(&self, word_index: usize, mask: usize, order: Ordering)
+		-> Option<usize>
+	{
+		let word = self.words.get(word_index)?;
+		Some(word.fetch_or(mask & self.used_bits(word_index), order))
+	}
+	// Copies the bits, loading each word with `order`.
+	// This is only a consistent snapshot if no other thread is modifying the bits.
+	pub fn snapshot(&self, order: Ordering) -> BitVector {
+		let words = self.words.iter().map(|word| word.load(order)).collect();
+		BitVector { len: self.len, words }
+	}
+	pub fn into_bit_vector(self) -> BitVector {
+		let words = self.words.into_iter().map(AtomicUsize::into_inner).collect();
+		BitVector { len: self.len, words }
+	}
+
+	fn locate(&self, index: usize) -> Option<(&AtomicUsize, usize)> {
+		if index >= self.len { return None }
+
+		Some((&self.words[index / WORD_BITS], 1 << (index % WORD_BITS)))
+	}
+	fn used_bits(&self, word_index: usize) -> usize {
+		let bits = self.len - word_index * WORD_BITS;
+		if bits < WORD_BITS { (1 << bits) - 1 } else { !0 }
+	}
+}
+
+impl From<BitVector> for AtomicBitVector {
+	fn from(mut bits: BitVector) -> Self {
+		bits.clear_unused_bits();
+		let words = bits.words.into_iter().map(AtomicUsize::new).collect();
+		AtomicBitVector { len: bits.len, words }
+	}
+}
+
+impl Debug for AtomicBitVector {
+	fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+		self.snapshot(Ordering::Relaxed).fmt(fmt)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::FromIterator;
+	use std::thread;
+
+	#[test]
+	fn test_single_thread() {
+		let bits = AtomicBitVector::new(100);
+		assert_eq!(bits.word_count(), 100usize.div_ceil(WORD_BITS));
+		assert_eq!(bits.test_and_set(5, Ordering::Relaxed), Some(false));
+		assert_eq!(bits.test_and_set(5, Ordering::Relaxed), Some(true));
+		assert_eq!(bits.load(5, Ordering::Relaxed), Some(true));
+		assert_eq!(bits.load(6, Ordering::Relaxed), Some(false));
+		assert_eq!(bits.clear(5, Ordering::Relaxed), Some(true));
+		assert_eq!(bits.clear(5, Ordering::Relaxed), Some(false));
+		assert_eq!(bits.test_and_set(100, Ordering::Relaxed), None);
+		assert_eq!(bits.load(100, Ordering::Relaxed), None);
+
+		// Bits past the end of the last word must stay clear
+		let last_word = bits.word_count() - 1;
+		assert_eq!(bits.fetch_or_word(last_word, !0, Ordering::Relaxed), Some(0));
+		assert_eq!(bits.fetch_or_word(last_word + 1, !0, Ordering::Relaxed), None);
+		assert_eq!(bits.snapshot(Ordering::Relaxed).count_ones(), 100 - last_word * WORD_BITS);
+		let expected = BitVector::from_iter((0..100).map(|i| i >= last_word * WORD_BITS));
+		assert_eq!(bits.into_bit_vector(), expected);
+	}
+
+	#[test]
+	fn test_from_bit_vector() {
+		let mut vector = BitVector::from_iter((0..130).map(|i| i % 3 == 0));
+		vector.pop();
+		let bits = AtomicBitVector::from(vector.clone());
+		assert_eq!(bits.len(), 129);
+		for i in 0..129 { assert_eq!(bits.load(i, Ordering::Relaxed), Some(i % 3 == 0)) }
+		assert_eq!(bits.snapshot(Ordering::Relaxed), vector);
+		assert_eq!(format!("{:?}", bits), format!("{:?}", vector));
+	}
+
+	#[test]
+	fn test_concurrent_marking() {
+		const THREADS: usize = 8;
+		let bits = AtomicBitVector::new(10_000);
+		let first_marks: Vec<Vec<usize>> = thread::scope(|scope| {
+			let handles: Vec<_> = (0..THREADS).map(|thread| {
+				let bits = &bits;
+				scope.spawn(move || {
+					// Every thread visits every node, starting at a different place
+					(0..bits.len())
+						.map(|i| (i + thread * 1234) % bits.len())
+						.filter(|&i| !bits.test_and_set(i, Ordering::Relaxed).unwrap())
+						.collect()
+				})
+			}).collect();
+			handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+		});
+		// Each node was marked first by exactly one thread
+		let mut marked: Vec<_> = first_marks.into_iter().flatten().collect();
+		marked.sort_unstable();
+		assert!(marked.into_iter().eq(0..10_000));
+		assert_eq!(bits.into_bit_vector().count_ones(), 10_000);
+	}
+}
@@ -1,46 +1,173 @@
 use std::cmp::Ordering;
 use std::fmt::{Debug, Formatter, Result};
-use std::iter::{FromIterator, IntoIterator};
+use std::iter::{FromIterator, FusedIterator, IntoIterator};
 use std::mem;
-use std::ptr;
-
-pub struct BitVector {
+use std::ops::{Bound, RangeBounds};
+
+mod array;
+mod atomic;
+mod bloom;
+mod bp_tree;
+mod bytes;
+mod compressed;
+mod edit;
+mod elias_fano;
+mod hierarchical;
+mod int_vector;
+mod matrix;
+mod ones;
+mod ops;
+mod range;
+mod rank_select;
+mod slice;
+mod stream;
+mod wavelet;
+mod word;
+
+pub use array::{words_for_bits, BitArray};
+pub use atomic::AtomicBitVector;
+pub use bloom::{BloomFilter, CountingBloomFilter};
+pub use bp_tree::{BpTree, BpTreeBuilder};
+pub use bytes::{BitOrder, Bytes};
+pub use compressed::CompressedBitVector;
+pub use elias_fano::EliasFano;
+pub use hierarchical::HierarchicalBitSet;
+pub use int_vector::IntVector;
+pub use matrix::BitMatrix;
+pub use ones::{IterOnes, IterZeros};
+pub use rank_select::RankSelect;
+pub use slice::{BitSlice, Chunks};
+pub use stream::{BitReader, BitWriter};
+pub use wavelet::WaveletMatrix;
+pub use word::{Storage, StorageMut, Word};
+
+// Bits packed into the words of any Storage, e.g. GenericBitVector<Vec<u8>>
+// or GenericBitVector<&mut [u32]>. Only Vec storage can grow.
+pub struct GenericBitVector<S> {
 	len: usize,
-	words: Vec<usize>,
+	words: S,
 }
 
+pub type BitVector = GenericBitVector<Vec<usize>>;
+
 const WORD_BITS: usize = mem::size_of::<usize>() * 8;
-const LOG_WORD_BITS: u8 = WORD_BITS.trailing_zeros() as u8;
 
-impl BitVector {
+impl<W: Word> GenericBitVector<Vec<W>> {
 	pub fn new() -> Self {
-		BitVector { len: 0, words: Vec::new() }
+		GenericBitVector { len: 0, words: Vec::new() }
 	}
 	pub fn with_capacity(capacity: usize) -> Self {
-		BitVector { len: 0, words: Vec::with_capacity(Self::to_words_ceil(capacity)) }
+		GenericBitVector { len: 0, words: Vec::with_capacity(Self::to_words_ceil(capacity)) }
 	}
 
 	pub fn capacity(&self) -> usize {
 		Self::from_word_index(self.words.capacity())
 	}
-	pub fn clear(&mut self) {
-		self.len = 0
+	pub fn push(&mut self, value: bool) {
+		let word_index = Self::to_word_index(self.len);
+		if word_index == self.words.len() {
+			self.words.push(W::from_bool(value))
+		}
+		else {
+			unsafe { self.set_unchecked(self.len, value) }
+		}
+		self.len += 1;
 	}
-	pub fn fill(&mut self, value: bool) {
-		let filled_word = Self::fill_word(value);
-		for word in &mut self.words[..Self::to_words_ceil(self.len)] {
-			*word = filled_word
+
+	// `bits` may start at any offset
+	pub fn extend_from_bitvector(&mut self, bits: &BitSlice<W>) {
+		let start = self.len;
+		let word_count = Self::to_words_ceil(start + bits.len());
+		if word_count > self.words.len() { self.words.resize(word_count, W::ZERO) }
+		self.len += bits.len();
+		self.store_bits(start, bits);
+	}
+	// Appends all bits of each byte, least significant first
+	pub fn extend_from_bytes(&mut self, bytes: &[u8]) {
+		let words = bytes.chunks(W::BITS / 8).map(|word_bytes| {
+			word_bytes.iter().rev().fold(W::ZERO, |word, &byte| {
+				// Shifting by W::BITS would overflow for u8 words
+				(if W::BITS > 8 { word << 8 } else { W::ZERO }) | W::from_u8(byte)
+			})
+		});
+		self.extend_words(words, bytes.len() * 8)
+	}
+	pub fn extend_from_words(&mut self, words: &[W]) {
+		self.extend_words(words.iter().cloned(), words.len() * W::BITS)
+	}
+
+	// Appends the low `bits` bits of `word`, for bits <= W::BITS
+	fn push_word(&mut self, word: W, bits: usize) {
+		let start = self.len;
+		let word_count = Self::to_words_ceil(start + bits);
+		if word_count > self.words.len() { self.words.resize(word_count, W::ZERO) }
+		self.len += bits;
+		self.store_word(start, word, bits);
+	}
+
+	// Appends the first `bits` bits of `words`
+	fn extend_words<I: Iterator<Item=W>>(&mut self, words: I, mut bits: usize) {
+		if Self::to_word_offset(self.len) == 0 {
+			// Whole words can be copied directly
+			self.words.truncate(Self::to_word_index(self.len));
+			self.words.extend(words);
+			self.len += bits;
 		}
+		else {
+			for word in words {
+				let word_bits = bits.min(W::BITS);
+				self.push_word(word, word_bits);
+				bits -= word_bits;
+			}
+		}
+	}
+
+	// The words holding the bits, with the bits past len() zeroed
+	pub fn into_words(mut self) -> Vec<W> {
+		self.clear_unused_bits();
+		self.words
+	}
+
+	// Drops any words past the end of the vector and zeroes the unused bits
+	// of the last word, so whole words can be inspected without masking
+	fn clear_unused_bits(&mut self) {
+		let word_count = Self::to_words_ceil(self.len);
+		self.words.truncate(word_count);
+		let extra_bits = Self::to_word_offset(self.len) as usize;
+		if extra_bits > 0 { self.words[word_count - 1] &= W::low_mask(extra_bits) }
+	}
+}
+
+impl<S: Storage> GenericBitVector<S> {
+	// Uses the first `len` bits of `words`, or returns None if there are fewer
+	pub fn from_storage(words: S, len: usize) -> Option<Self> {
+		if Self::to_words_ceil(len) > words.as_words().len() { return None }
+
+		Some(GenericBitVector { len, words })
+	}
+	pub fn into_storage(self) -> S {
+		self.words
+	}
+	// The words holding the bits. Bits past len() in the last word are unspecified.
+	pub fn as_words(&self) -> &[S::Word] {
+		&self.words.as_words()[..Self::to_words_ceil(self.len)]
+	}
+
+	pub fn clear(&mut self) {
+		self.len = 0
 	}
 	pub fn get(&self, index: usize) -> Option<bool> {
 		if index >= self.len { return None }
 
 		Some(unsafe { self.get_unchecked(index) })
 	}
+	/// # Safety
+	/// `index` must be less than `self.len()`
 	pub unsafe fn get_unchecked(&self, index: usize) -> bool {
 		let word_index = Self::to_word_index(index);
-		let word_offset = Self::to_word_offset(index);
-		*self.words.get_unchecked(word_index) >> word_offset & 1 > 0
+		let word_offset = Self::to_word_offset(index) as usize;
+		*self.words.as_words().get_unchecked(word_index) >> word_offset & S::Word::ONE !=
+			S::Word::ZERO
 	}
 	pub fn is_empty(&self) -> bool {
 		self.len == 0
@@ -54,15 +181,31 @@ impl BitVector {
 		self.len -= 1;
 		Some(unsafe { self.get_unchecked(self.len) })
 	}
-	pub fn push(&mut self, value: bool) {
-		let word_index = Self::to_word_index(self.len);
-		if word_index == self.words.len() {
-			self.words.push(value as usize)
-		}
-		else {
-			unsafe { self.set_unchecked(self.len, value) }
+
+	fn to_word_index(bit_index: usize) -> usize {
+		bit_index >> S::Word::LOG_BITS
+	}
+	fn to_word_offset(bit_index: usize) -> u8 {
+		(bit_index & (S::Word::BITS - 1)) as u8
+	}
+	fn to_words_ceil(bits: usize) -> usize {
+		Self::to_word_index(bits) + (Self::to_word_offset(bits) > 0) as usize
+	}
+	fn from_word_index(word_index: usize) -> usize {
+		word_index << S::Word::LOG_BITS
+	}
+	fn fill_word(value: bool) -> S::Word {
+		S::Word::fill(value)
+	}
+}
+
+impl<S: StorageMut> GenericBitVector<S> {
+	pub fn fill(&mut self, value: bool) {
+		let filled_word = Self::fill_word(value);
+		let word_count = Self::to_words_ceil(self.len);
+		for word in &mut self.words.as_words_mut()[..word_count] {
+			*word = filled_word
 		}
-		self.len += 1;
 	}
 	pub fn set(&mut self, index: usize, value: bool) -> Option<()> {
 		if index >= self.len { return None }
@@ -70,181 +213,195 @@ impl BitVector {
 		unsafe { self.set_unchecked(index, value) }
 		Some(())
 	}
+	/// # Safety
+	/// `index` must be less than `self.len()`
 	pub unsafe fn set_unchecked(&mut self, index: usize, value: bool) {
-		let word = self.words.get_unchecked_mut(Self::to_word_index(index));
-		let set_bit = 1 << Self::to_word_offset(index);
+		let word_index = Self::to_word_index(index);
+		let set_bit = S::Word::ONE << Self::to_word_offset(index) as usize;
+		let word = self.words.as_words_mut().get_unchecked_mut(word_index);
 		*word = *word & !set_bit | Self::fill_word(value) & set_bit;
 	}
+}
 
-	pub fn bytes(&self) -> Bytes {
-		Bytes {
-			bit_index: 0,
-			bit_len: self.len,
-			current_word: if self.is_empty() { ptr::null() } else { self.words.as_ptr() }
-		}
-	}
+// Converts a range into start and end indices, if it lies within 0..len
+fn range_bounds<R: RangeBounds<usize>>(range: R, len: usize) -> Option<(usize, usize)> {
+	use Bound::*;
+
+	let start = match range.start_bound() {
+		Included(&start) => start,
+		Excluded(&start) => start.checked_add(1)?,
+		Unbounded => 0,
+	};
+	let end = match range.end_bound() {
+		Included(&end) => end.checked_add(1)?,
+		Excluded(&end) => end,
+		Unbounded => len,
+	};
+	if start <= end && end <= len { Some((start, end)) } else { None }
+}
 
-	fn to_word_index(bit_index: usize) -> usize {
-		bit_index >> LOG_WORD_BITS
-	}
-	fn to_word_offset(bit_index: usize) -> u8 {
-		(bit_index & (WORD_BITS - 1)) as u8
-	}
-	fn to_words_ceil(bits: usize) -> usize {
-		Self::to_word_index(bits) + (Self::to_word_offset(bits) > 0) as usize
-	}
-	fn from_word_index(word_index: usize) -> usize {
-		word_index << LOG_WORD_BITS
-	}
-	fn fill_word(value: bool) -> usize {
-		-(value as isize) as usize
+impl<W: Word> Default for GenericBitVector<Vec<W>> {
+	fn default() -> Self {
+		Self::new()
 	}
 }
 
-impl Clone for BitVector {
+impl<W: Word> Clone for GenericBitVector<Vec<W>> {
 	fn clone(&self) -> Self {
-		BitVector {
+		GenericBitVector {
 			len: self.len,
 			words: self.words[..Self::to_words_ceil(self.len)].to_vec()
 		}
 	}
 }
+impl<W: Word, const N: usize> Clone for GenericBitVector<[W; N]> {
+	fn clone(&self) -> Self {
+		GenericBitVector { len: self.len, words: self.words }
+	}
+}
 
-impl Debug for BitVector {
+impl<S: Storage> Debug for GenericBitVector<S> {
 	fn fmt(&self, fmt: &mut Formatter) -> Result {
-		fmt.debug_list().entries(self.into_iter().map(|b| b as u8)).finish()
+		Debug::fmt(&**self, fmt)
 	}
 }
 
-impl PartialEq for BitVector {
-	fn eq(&self, other: &Self) -> bool {
+impl<S: Storage, T: Storage<Word=S::Word>> PartialEq<GenericBitVector<T>> for GenericBitVector<S> {
+	fn eq(&self, other: &GenericBitVector<T>) -> bool {
 		if self.len != other.len { return false }
 
+		let (words, other_words) = (self.words.as_words(), other.words.as_words());
 		let full_words = Self::to_word_index(self.len);
-		if self.words[..full_words] != other.words[..full_words] { return false }
+		if words[..full_words] != other_words[..full_words] { return false }
 
-		let bit_compare_index = Self::from_word_index(full_words);
-		let extra_bits = self.len - bit_compare_index;
+		let extra_bits = Self::to_word_offset(self.len) as usize;
 		if extra_bits == 0 { return true }
 
-		let unset_bits = WORD_BITS - extra_bits;
-		self.words[full_words] << unset_bits == other.words[full_words] << unset_bits
+		let used_bits = S::Word::low_mask(extra_bits);
+		words[full_words] & used_bits == other_words[full_words] & used_bits
 	}
 }
-impl Eq for BitVector {}
-impl Ord for BitVector {
+impl<S: Storage> Eq for GenericBitVector<S> {}
+impl<S: Storage> Ord for GenericBitVector<S> {
 	fn cmp(&self, other: &Self) -> Ordering {
-		self.into_iter().cmp(other.into_iter())
+		(**self).cmp(other)
 	}
 }
-impl PartialOrd for BitVector {
+impl<S: Storage> PartialOrd for GenericBitVector<S> {
 	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
 		Some(self.cmp(other))
 	}
 }
 
-impl Extend<bool> for BitVector {
+impl<W: Word> Extend<bool> for GenericBitVector<Vec<W>> {
 	fn extend<I: IntoIterator<Item=bool>>(&mut self, values: I) {
 		let iter = values.into_iter();
 		let (additional, _) = iter.size_hint();
-		self.words.reserve(Self::to_words_ceil(additional));
-		for value in iter { self.push(value) }
+		let word_count = Self::to_words_ceil(self.len + additional);
+		self.words.reserve(word_count.saturating_sub(self.words.len()));
+		// Collect a word of bits at a time
+		let (mut word, mut bits) = (W::ZERO, 0);
+		for value in iter {
+			word |= W::from_bool(value) << bits;
+			bits += 1;
+			if bits == W::BITS {
+				self.push_word(word, bits);
+				word = W::ZERO;
+				bits = 0;
+			}
+		}
+		self.push_word(word, bits);
 	}
 }
-impl<'a> Extend<&'a bool> for BitVector {
+impl<'a, W: Word> Extend<&'a bool> for GenericBitVector<Vec<W>> {
 	fn extend<I: IntoIterator<Item=&'a bool>>(&mut self, values: I) {
 		self.extend(values.into_iter().cloned())
 	}
 }
 
-impl FromIterator<bool> for BitVector {
+impl<W: Word> FromIterator<bool> for GenericBitVector<Vec<W>> {
 	fn from_iter<I: IntoIterator<Item=bool>>(iter: I) -> Self {
-		let mut result = BitVector::new();
+		let mut result = Self::new();
 		result.extend(iter);
 		result
 	}
 }
-impl<'a> FromIterator<&'a bool> for BitVector {
+impl<'a, W: Word> FromIterator<&'a bool> for GenericBitVector<Vec<W>> {
 	fn from_iter<I: IntoIterator<Item=&'a bool>>(iter: I) -> Self {
 		Self::from_iter(iter.into_iter().cloned())
 	}
 }
 
-pub struct IntoIter {
-	bits: BitVector,
-	index: usize,
-}
-
-impl Iterator for IntoIter {
-	type Item = bool;
-
-	fn next(&mut self) -> Option<bool> {
-		let result = self.bits.get(self.index);
-		self.index += 1;
-		result
-	}
+// The bits in start..end remain to be yielded
+pub struct IntoIter<S = Vec<usize>> {
+	bits: GenericBitVector<S>,
+	start: usize,
+	end: usize,
 }
 
-pub struct Iter<'a> {
-	bits: &'a BitVector,
-	index: usize,
+pub struct Iter<'a, W: Word = usize> {
+	bits: &'a BitSlice<W>,
+	start: usize,
+	end: usize,
 }
 
-impl<'a> Iterator for Iter<'a> {
-	type Item = bool;
+macro_rules! bit_iterator {
+	($iter:ty, $($generics:tt)*) => {
+		impl<$($generics)*> Iterator for $iter {
+			type Item = bool;
 
-	fn next(&mut self) -> Option<bool> {
-		let result = self.bits.get(self.index);
-		self.index += 1;
-		result
-	}
-}
+			fn next(&mut self) -> Option<bool> {
+				if self.start == self.end { return None }
 
-impl IntoIterator for BitVector {
-	type Item = bool;
-	type IntoIter = IntoIter;
+				let result = unsafe { self.bits.get_unchecked(self.start) };
+				self.start += 1;
+				Some(result)
+			}
+			fn size_hint(&self) -> (usize, Option<usize>) {
+				let len = self.end - self.start;
+				(len, Some(len))
+			}
+			fn nth(&mut self, n: usize) -> Option<bool> {
+				self.start += n.min(self.end - self.start);
+				self.next()
+			}
+		}
+		impl<$($generics)*> DoubleEndedIterator for $iter {
+			fn next_back(&mut self) -> Option<bool> {
+				if self.start == self.end { return None }
 
-	fn into_iter(self) -> IntoIter {
-		IntoIter { bits: self, index: 0 }
-	}
+				self.end -= 1;
+				Some(unsafe { self.bits.get_unchecked(self.end) })
+			}
+			fn nth_back(&mut self, n: usize) -> Option<bool> {
+				self.end -= n.min(self.end - self.start);
+				self.next_back()
+			}
+		}
+		impl<$($generics)*> ExactSizeIterator for $iter {}
+		impl<$($generics)*> FusedIterator for $iter {}
+	};
 }
 
-impl<'a> IntoIterator for &'a BitVector {
+bit_iterator!(IntoIter<S>, S: Storage);
+bit_iterator!(Iter<'_, W>, W: Word);
+
+impl<S: Storage> IntoIterator for GenericBitVector<S> {
 	type Item = bool;
-	type IntoIter = Iter<'a>;
+	type IntoIter = IntoIter<S>;
 
-	fn into_iter(self) -> Iter<'a> {
-		Iter { bits: self, index: 0 }
+	fn into_iter(self) -> IntoIter<S> {
+		let end = self.len;
+		IntoIter { bits: self, start: 0, end }
 	}
 }
 
-pub struct Bytes {
-	bit_index: usize,
-	bit_len: usize,
-	current_word: *const usize,
-}
-
-impl Iterator for Bytes {
-	type Item = u8;
-
-	fn next(&mut self) -> Option<u8> {
-		if self.current_word.is_null() { return None }
+impl<'a, S: Storage> IntoIterator for &'a GenericBitVector<S> {
+	type Item = bool;
+	type IntoIter = Iter<'a, S::Word>;
 
-		let word_offset = BitVector::to_word_offset(self.bit_index);
-		let mut byte;
-		unsafe {
-			if self.bit_index > 0 && word_offset == 0 {
-				self.current_word = self.current_word.add(1)
-			}
-			byte = (*self.current_word >> word_offset) as u8;
-		}
-		self.bit_index += 8;
-		let unset_bits = self.bit_index as isize - self.bit_len as isize;
-		if unset_bits >= 0 {
-			byte = byte << unset_bits >> unset_bits;
-			self.current_word = ptr::null();
-		}
-		Some(byte)
+	fn into_iter(self) -> Iter<'a, S::Word> {
+		(**self).into_iter()
 	}
 }
 
@@ -255,7 +412,7 @@ mod tests {
 
 	#[test]
 	fn test_get_set() {
-		let mut bit_vec = BitVector::from_iter(iter::repeat(false).take(1000));
+		let mut bit_vec = BitVector::from_iter(iter::repeat_n(false, 1000));
 		for i in 1000..2000 { assert_eq!(bit_vec.set(i, false), None) }
 		for i in 0..1000 {
 			let fill_value = i & 1 > 0;
@@ -289,7 +446,7 @@ mod tests {
 
 	#[test]
 	fn test_fill() {
-		let mut bit_vec = BitVector::from_iter(iter::repeat(false).take(100000));
+		let mut bit_vec = BitVector::from_iter(iter::repeat_n(false, 100000));
 		assert_eq!(bit_vec.len(), 100000);
 		for i in 0..100000 { assert_eq!(bit_vec.get(i), Some(false)) }
 		bit_vec.fill(true);
@@ -328,7 +485,7 @@ mod tests {
 		// Test multiple bytes and words
 		bit_vec.clear();
 		for i in 0..=255 {
-			let mut byte = i as u8;
+			let mut byte = i;
 			for _ in 0..8 {
 				bit_vec.push(byte & 1 > 0);
 				byte >>= 1;
@@ -336,4 +493,124 @@ mod tests {
 			assert!(bit_vec.bytes().eq(0..=i));
 		}
 	}
-}
\ No newline at end of file
+
+	fn check_word_type<W: Word>() {
+		let values: Vec<_> = (0..300).map(|i| (i * i + i / 5) % 3 == 0).collect();
+		let mut bits = GenericBitVector::<Vec<W>>::from_iter(&values);
+		assert!(bits.iter().eq(values.iter().cloned()));
+		assert!(bits.iter_ones().eq((0..300).filter(|&i| values[i])));
+		assert_eq!(bits.count_ones(), values.iter().filter(|&&value| value).count());
+		assert_eq!(bits[10..20], GenericBitVector::<Vec<W>>::from_iter(&values[10..20])[..]);
+		for (i, value) in values.iter().enumerate() { bits.set(i, !value); }
+		assert!(bits.iter().eq(values.iter().map(|value| !value)));
+		for i in (0..300).rev() { assert_eq!(bits.pop(), Some(!values[i])) }
+		assert_eq!(bits.pop(), None);
+	}
+
+	#[test]
+	fn test_word_types() {
+		check_word_type::<u8>();
+		check_word_type::<u16>();
+		check_word_type::<u32>();
+		check_word_type::<u64>();
+		check_word_type::<u128>();
+		check_word_type::<usize>();
+	}
+
+	fn check_extend<W: Word>() {
+		let values: Vec<_> = (0..300).map(|i| (i * i + i / 7) % 5 < 2).collect();
+		let source = GenericBitVector::<Vec<W>>::from_iter(&values);
+		for prefix_len in 0..=W::BITS + 1 {
+			for start in (0..=300).step_by(23) {
+				let mut bits = GenericBitVector::<Vec<W>>::from_iter(&values[..prefix_len]);
+				bits.extend_from_bitvector(&source[start..]);
+				let expected = values[..prefix_len].iter().chain(&values[start..]);
+				assert!(bits.iter().eq(expected.cloned()));
+			}
+
+			let bytes = [0b1010_0110u8, 0xFF, 0, 0b1];
+			let byte_bits = bytes.iter().flat_map(|&byte| (0..8).map(move |i| byte >> i & 1 != 0));
+			let mut bits = GenericBitVector::<Vec<W>>::from_iter(&values[..prefix_len]);
+			bits.extend_from_bytes(&bytes);
+			bits.extend_from_words(&[W::ONE, W::ONES]);
+			let word_bits = (0..2 * W::BITS).map(|i| i == 0 || i >= W::BITS);
+			let expected: Vec<_> =
+				values[..prefix_len].iter().cloned().chain(byte_bits).chain(word_bits).collect();
+			assert!(bits.iter().eq(expected));
+		}
+	}
+
+	#[test]
+	fn test_extend() {
+		check_extend::<u8>();
+		check_extend::<u32>();
+		check_extend::<u128>();
+		check_extend::<usize>();
+
+		// Extending over popped bits must not expose their old values
+		let mut bits = BitVector::from_iter(vec![true; 200]);
+		for _ in 0..136 { bits.pop(); }
+		bits.extend(vec![false; 10]);
+		bits.extend_from_words(&[0]);
+		assert_eq!(bits.count_ones(), 64);
+		assert_eq!(bits.len(), 64 + 10 + WORD_BITS);
+		let mut bits = BitVector::from_iter(vec![true; 200]);
+		for _ in 0..(200 - WORD_BITS) { bits.pop(); }
+		bits.extend_from_bytes(&[0; 20]);
+		assert_eq!(bits.count_ones(), WORD_BITS);
+		assert_eq!(bits.len(), WORD_BITS + 160);
+	}
+
+	#[test]
+	fn test_iter() {
+		let values: Vec<_> = (0..200).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+		let bits = BitVector::from_iter(&values);
+		assert_eq!(bits.iter().len(), 200);
+		assert!(bits.iter().rev().eq(values.iter().rev().cloned()));
+		for n in 0..210 {
+			assert_eq!(bits.iter().nth(n), values.get(n).cloned());
+			assert_eq!(bits.iter().nth_back(n), values.iter().rev().nth(n).cloned());
+			assert_eq!(bits.iter().skip(n).len(), 200usize.saturating_sub(n));
+		}
+
+		// Alternate between the ends, then check the iterator stays finished
+		let mut iter = bits.clone().into_iter();
+		let mut expected = values.iter().cloned();
+		for i in 0..100 {
+			if i % 2 == 0 { assert_eq!(iter.next(), expected.next()) }
+			else { assert_eq!(iter.next_back(), expected.next_back()) }
+			assert_eq!(iter.len(), expected.len());
+		}
+		assert!(iter.by_ref().eq(expected));
+		assert_eq!(iter.next(), None);
+		assert_eq!(iter.next_back(), None);
+		let mut iter = bits[5..15].iter();
+		assert_eq!(iter.nth(20), None);
+		assert_eq!(iter.next(), None);
+	}
+
+	#[test]
+	fn test_fixed_storage() {
+		let mut bytes = [0u8; 4];
+		{
+			let mut bits = GenericBitVector::from_storage(&mut bytes[..], 30).unwrap();
+			bits.set(0, true);
+			bits.set(9, true);
+			bits.set(29, true);
+			assert_eq!(bits.set(30, true), None);
+			assert!(bits.iter_ones().eq(vec![0, 9, 29]));
+		}
+		assert_eq!(bytes, [0b1, 0b10, 0, 0b10_0000]);
+		assert!(GenericBitVector::from_storage(&mut bytes[..], 33).is_none());
+
+		let borrowed = GenericBitVector::from_storage(&bytes[..], 16).unwrap();
+		assert_eq!(borrowed.get(9), Some(true));
+		assert_eq!(borrowed, GenericBitVector::<Vec<u8>>::from_iter((0..16).map(|i| i % 9 == 0)));
+
+		let mut array = GenericBitVector::from_storage([0u64; 2], 128).unwrap();
+		array.fill(true);
+		array[64..].fill_bits(0, 64, false);
+		let copy = array.clone();
+		assert_eq!(copy.into_storage(), [!0, 0]);
+	}
+}
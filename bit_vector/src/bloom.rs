@@ -0,0 +1,357 @@
+use std::f64::consts::LN_2;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use super::{BitVector, WORD_BITS};
+
+const COUNTER_BITS: usize = 4;
+const MAX_COUNT: usize = (1 << COUNTER_BITS) - 1;
+// An optimally sized filter with this many hashes has a false-positive rate of about 2^-64
+const MAX_HASH_COUNT: u32 = 64;
+
+// Optimal (bit count, hash count) for the expected number of items and false-positive rate
+fn optimal_params(expected_items: usize, false_positive_rate: f64) -> (usize, u32) {
+	assert!(
+		false_positive_rate > 0.0 && false_positive_rate < 1.0,
+		"False-positive rate must be between 0 and 1",
+	);
+	let items = expected_items.max(1) as f64;
+	let bit_count = (-items * false_positive_rate.ln() / (LN_2 * LN_2)).ceil();
+	let hash_count = (bit_count / items * LN_2).round().max(1.0).min(MAX_HASH_COUNT as f64);
+	(bit_count as usize, hash_count as u32)
+}
+
+// FNV-1a with a final mix, so saved filters stay valid across Rust releases and platforms.
+// Integers are hashed as little-endian bytes, and usize and isize as 64-bit integers.
+struct StableHasher(u64);
+
+impl StableHasher {
+	fn new() -> Self {
+		StableHasher(0xcbf2_9ce4_8422_2325)
+	}
+}
+
+impl Hasher for StableHasher {
+	fn write(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100_0000_01b3);
+		}
+	}
+	fn write_u16(&mut self, value: u16) {
+		self.write(&value.to_le_bytes())
+	}
+	fn write_u32(&mut self, value: u32) {
+		self.write(&value.to_le_bytes())
+	}
+	fn write_u64(&mut self, value: u64) {
+		self.write(&value.to_le_bytes())
+	}
+	fn write_u128(&mut self, value: u128) {
+		self.write(&value.to_le_bytes())
+	}
+	fn write_usize(&mut self, value: usize) {
+		self.write_u64(value as u64)
+	}
+	fn write_isize(&mut self, value: isize) {
+		self.write_u64(value as i64 as u64)
+	}
+	// FNV mixes the last bytes poorly, so finish with the SplitMix64 finalizer
+	fn finish(&self) -> u64 {
+		let mut hash = self.0;
+		hash = (hash ^ hash >> 30).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		hash = (hash ^ hash >> 27).wrapping_mul(0x94d0_49bb_1331_11eb);
+		hash ^ hash >> 31
+	}
+}
+
+// The positions of `item` in a filter with `slots` slots, using double hashing:
+// position i is h1 + i * h2, where h1 and h2 come from differently seeded hashes
+fn positions<T: Hash + ?Sized>(item: &T, slots: usize, hash_count: u32)
+	-> impl Iterator<Item=usize>
+{
+	let hash = |seed: u64| {
+		let mut hasher = StableHasher::new();
+		seed.hash(&mut hasher);
+		item.hash(&mut hasher);
+		hasher.finish()
+	};
+	let (h1, h2) = (hash(0), hash(1) | 1);
+	let slots = slots as u64;
+	(0..hash_count as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % slots) as usize)
+}
+
+fn zeros(len: usize) -> BitVector {
+	BitVector::from_storage(vec![0; len.div_ceil(WORD_BITS)], len).unwrap()
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub struct BloomFilter<T: Hash + ?Sized> {
+	bits: BitVector,
+	hash_count: u32,
+	_item: PhantomData<fn(&T)>,
+}
+
+impl<T: Hash + ?Sized> BloomFilter<T> {
+	pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+		let (bit_count, hash_count) = optimal_params(expected_items, false_positive_rate);
+		Self::with_params(bit_count, hash_count)
+	}
+	pub fn with_params(bit_count: usize, hash_count: u32) -> Self {
+		assert!(bit_count > 0 && hash_count > 0, "Filter must have bits and hashes");
+		assert!(hash_count <= MAX_HASH_COUNT, "Too many hashes");
+		BloomFilter { bits: zeros(bit_count), hash_count, _item: PhantomData }
+	}
+	pub fn bits(&self) -> &BitVector {
+		&self.bits
+	}
+	pub fn hash_count(&self) -> u32 {
+		self.hash_count
+	}
+	pub fn insert(&mut self, item: &T) {
+		for position in positions(item, self.bits.len(), self.hash_count) {
+			unsafe { self.bits.set_unchecked(position, true) }
+		}
+	}
+	// Never false for inserted items; may be true for items that were not inserted
+	pub fn contains(&self, item: &T) -> bool {
+		positions(item, self.bits.len(), self.hash_count)
+			.all(|position| unsafe { self.bits.get_unchecked(position) })
+	}
+	pub fn clear(&mut self) {
+		self.bits.fill(false)
+	}
+	// Filters are compatible if they have the same bit count and hash count
+	pub fn is_compatible(&self, other: &Self) -> bool {
+		self.bits.len() == other.bits.len() && self.hash_count == other.hash_count
+	}
+	// Afterwards, contains every item of either filter.
+	// Returns None if the filters are incompatible.
+	pub fn union_with(&mut self, other: &Self) -> Option<()> {
+		if !self.is_compatible(other) { return None }
+
+		self.bits |= &other.bits;
+		Some(())
+	}
+	// Afterwards, contains every item of both filters,
+	// though the false-positive rate may be higher than a filter built from them
+	pub fn intersect_with(&mut self, other: &Self) -> Option<()> {
+		if !self.is_compatible(other) { return None }
+
+		self.bits &= &other.bits;
+		Some(())
+	}
+
+	// Writes the hash count as a little-endian u32, followed by the bits in write_to() form
+	pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+		writer.write_all(&self.hash_count.to_le_bytes())?;
+		self.bits.write_to(writer)
+	}
+	pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+		let mut hash_count_bytes = [0; 4];
+		reader.read_exact(&mut hash_count_bytes)?;
+		let hash_count = u32::from_le_bytes(hash_count_bytes);
+		let bits = BitVector::read_from(reader)?;
+		if bits.is_empty() || hash_count == 0 {
+			return Err(invalid_data("Filter must have bits and hashes"))
+		}
+		if hash_count > MAX_HASH_COUNT { return Err(invalid_data("Too many hashes")) }
+		Ok(BloomFilter { bits, hash_count, _item: PhantomData })
+	}
+}
+
+impl<T: Hash + ?Sized> Clone for BloomFilter<T> {
+	fn clone(&self) -> Self {
+		BloomFilter { bits: self.bits.clone(), hash_count: self.hash_count, _item: PhantomData }
+	}
+}
+
+impl<T: Hash + ?Sized> Debug for BloomFilter<T> {
+	fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+		fmt.debug_struct("BloomFilter")
+			.field("bit_count", &self.bits.len())
+			.field("hash_count", &self.hash_count)
+			.field("ones", &self.bits.count_ones())
+			.finish()
+	}
+}
+
+// A Bloom filter with a 4-bit counter in place of each bit, so items can be removed.
+// Counters saturate at 15 and are never decremented afterwards,
+// so removals cannot cause false negatives.
+pub struct CountingBloomFilter<T: Hash + ?Sized> {
+	counters: BitVector,
+	hash_count: u32,
+	_item: PhantomData<fn(&T)>,
+}
+
+impl<T: Hash + ?Sized> CountingBloomFilter<T> {
+	pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+		let (counter_count, hash_count) = optimal_params(expected_items, false_positive_rate);
+		Self::with_params(counter_count, hash_count)
+	}
+	pub fn with_params(counter_count: usize, hash_count: u32) -> Self {
+		assert!(counter_count > 0 && hash_count > 0, "Filter must have counters and hashes");
+		assert!(hash_count <= MAX_HASH_COUNT, "Too many hashes");
+		let counters = zeros(counter_count * COUNTER_BITS);
+		CountingBloomFilter { counters, hash_count, _item: PhantomData }
+	}
+	pub fn counter_count(&self) -> usize {
+		self.counters.len() / COUNTER_BITS
+	}
+	pub fn hash_count(&self) -> u32 {
+		self.hash_count
+	}
+	pub fn insert(&mut self, item: &T) {
+		for position in positions(item, self.counter_count(), self.hash_count) {
+			let count = self.count(position);
+			if count < MAX_COUNT { self.set_count(position, count + 1) }
+		}
+	}
+	pub fn contains(&self, item: &T) -> bool {
+		positions(item, self.counter_count(), self.hash_count)
+			.all(|position| self.count(position) > 0)
+	}
+	// Removes an item that was inserted. Returns false, without changing the filter,
+	// if the item is definitely not present. Removing an item that was never inserted
+	// but is a false positive may cause false negatives for other items.
+	pub fn remove(&mut self, item: &T) -> bool {
+		if !self.contains(item) { return false }
+
+		for position in positions(item, self.counter_count(), self.hash_count) {
+			let count = self.count(position);
+			if count < MAX_COUNT { self.set_count(position, count - 1) }
+		}
+		true
+	}
+	pub fn clear(&mut self) {
+		self.counters.fill(false)
+	}
+	// A plain filter with the same membership answers
+	pub fn to_bloom_filter(&self) -> BloomFilter<T> {
+		let bits = (0..self.counter_count()).map(|position| self.count(position) > 0).collect();
+		BloomFilter { bits, hash_count: self.hash_count, _item: PhantomData }
+	}
+
+	fn count(&self, position: usize) -> usize {
+		self.counters.load_word(position * COUNTER_BITS) & MAX_COUNT
+	}
+	fn set_count(&mut self, position: usize, count: usize) {
+		self.counters.store_word(position * COUNTER_BITS, count, COUNTER_BITS)
+	}
+}
+
+impl<T: Hash + ?Sized> Clone for CountingBloomFilter<T> {
+	fn clone(&self) -> Self {
+		let counters = self.counters.clone();
+		CountingBloomFilter { counters, hash_count: self.hash_count, _item: PhantomData }
+	}
+}
+
+impl<T: Hash + ?Sized> Debug for CountingBloomFilter<T> {
+	fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+		fmt.debug_struct("CountingBloomFilter")
+			.field("counter_count", &self.counter_count())
+			.field("hash_count", &self.hash_count)
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_sizing() {
+		let filter = BloomFilter::<str>::new(1000, 0.01);
+		// About 9.6 bits and 7 hashes per item
+		assert_eq!(filter.bits().len(), 9586);
+		assert_eq!(filter.hash_count(), 7);
+		let filter = BloomFilter::<str>::new(0, 0.5);
+		assert!(!filter.bits().is_empty());
+		assert_eq!(filter.hash_count(), 1);
+	}
+
+	#[test]
+	fn test_stable_positions() {
+		// Saved filters depend on these never changing
+		assert!(positions("apple", 1000, 4).eq(vec![806, 87, 368, 649]));
+		assert!(positions(&12345u32, 1 << 20, 3).eq(vec![400799, 960266, 471157]));
+		assert_eq!(BloomFilter::<str>::new(10, 1e-30).hash_count(), MAX_HASH_COUNT);
+	}
+
+	#[test]
+	fn test_false_positive_rate() {
+		let mut filter = BloomFilter::new(10_000, 0.01);
+		for i in 0..10_000u32 { filter.insert(&i) }
+		for i in 0..10_000u32 { assert!(filter.contains(&i)) }
+		let false_positives = (10_000..110_000u32).filter(|i| filter.contains(i)).count();
+		assert!(false_positives < 1_500, "{} false positives", false_positives);
+		filter.clear();
+		assert!(!filter.contains(&0));
+	}
+
+	#[test]
+	fn test_union_intersection() {
+		let mut evens = BloomFilter::new(100, 0.001);
+		let mut odds = evens.clone();
+		let mut small = evens.clone();
+		for i in 0..100u32 {
+			if i % 2 == 0 { evens.insert(&i) } else { odds.insert(&i) }
+			if i < 50 { small.insert(&i) }
+		}
+		let mut union = evens.clone();
+		union.union_with(&odds).unwrap();
+		for i in 0..100u32 { assert!(union.contains(&i)) }
+		let mut intersection = small.clone();
+		intersection.intersect_with(&evens).unwrap();
+		for i in (0..50u32).step_by(2) { assert!(intersection.contains(&i)) }
+		assert!((50..100u32).filter(|i| intersection.contains(i)).count() < 5);
+
+		let mut other = BloomFilter::new(200, 0.001);
+		assert_eq!(other.union_with(&evens), None);
+		assert_eq!(other.intersect_with(&evens), None);
+	}
+
+	#[test]
+	fn test_write_read() {
+		let mut filter = BloomFilter::new(50, 0.01);
+		for word in &["apple", "banana", "cherry"] { filter.insert(*word) }
+		let mut written = vec![];
+		filter.write_to(&mut written).unwrap();
+		let loaded = BloomFilter::<str>::read_from(&written[..]).unwrap();
+		assert_eq!(loaded.bits(), filter.bits());
+		assert_eq!(loaded.hash_count(), filter.hash_count());
+		for word in &["apple", "banana", "cherry"] { assert!(loaded.contains(*word)) }
+
+		let error = BloomFilter::<str>::read_from(&written[..10]).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+		for &hash_count in &[0, MAX_HASH_COUNT + 1, u32::MAX] {
+			written[..4].copy_from_slice(&hash_count.to_le_bytes());
+			let error = BloomFilter::<str>::read_from(&written[..]).unwrap_err();
+			assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+		}
+	}
+
+	#[test]
+	fn test_counting() {
+		let mut filter = CountingBloomFilter::new(1000, 0.01);
+		for i in 0..1000u32 { filter.insert(&i) }
+		for i in 0..1000u32 { assert!(filter.contains(&i)) }
+		for i in (0..1000u32).step_by(2) { assert!(filter.remove(&i)) }
+		for i in (1..1000u32).step_by(2) { assert!(filter.contains(&i)) }
+		assert!((0..1000u32).step_by(2).filter(|i| filter.contains(i)).count() < 50);
+		let plain = filter.to_bloom_filter();
+		for i in 0..2000u32 { assert_eq!(plain.contains(&i), filter.contains(&i)) }
+
+		// Saturated counters are never decremented
+		let mut filter = CountingBloomFilter::with_params(1, 1);
+		for _ in 0..20 { filter.insert(&0u32) }
+		for _ in 0..20 { assert!(filter.remove(&0u32)) }
+		assert!(filter.contains(&0u32));
+		assert!(!CountingBloomFilter::with_params(10, 3).remove(&0u32));
+	}
+}
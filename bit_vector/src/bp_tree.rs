@@ -0,0 +1,499 @@
+use super::{BitVector, RankSelect};
+
+// The excess of a prefix of the parentheses is the number of opens minus closes.
+// Blocks of the excess values are summarized by their minimums in a segment tree,
+// so searches scan at most two partial blocks and walk O(log n) tree nodes.
+const BLOCK_BITS: usize = 512;
+
+// For each byte of parentheses (least significant bit first, 1 = open):
+// its total excess, the minimum excess after 1..=8 bits, and after 0..=7 bits
+static BYTE_TABLES: ([i8; 256], [i8; 256], [i8; 256]) = byte_tables();
+
+const fn byte_tables() -> ([i8; 256], [i8; 256], [i8; 256]) {
+	let mut total = [0; 256];
+	let mut forward_min = [0; 256];
+	let mut backward_min = [0; 256];
+	let mut byte = 0;
+	while byte < 256 {
+		let (mut excess, mut min_after, mut min_before) = (0, i8::MAX, 0);
+		let mut bit = 0;
+		while bit < 8 {
+			if excess < min_before { min_before = excess }
+			excess += if byte >> bit & 1 != 0 { 1 } else { -1 };
+			if excess < min_after { min_after = excess }
+			bit += 1;
+		}
+		total[byte] = excess;
+		forward_min[byte] = min_after;
+		backward_min[byte] = min_before;
+		byte += 1;
+	}
+	(total, forward_min, backward_min)
+}
+
+// An ordered tree stored as balanced parentheses: a depth-first traversal
+// writes 1 on entering a node and 0 on leaving it, so a tree of n nodes takes 2n bits.
+// Nodes are identified by the index of their opening parenthesis,
+// and methods given any other index return None.
+pub struct BpTree {
+	bits: RankSelect,
+	// A segment tree of minimum excess values. Leaf b covers prefix lengths
+	// b * BLOCK_BITS..=(b + 1) * BLOCK_BITS, and node i has children 2i and 2i + 1.
+	min_tree: Vec<isize>,
+	leaf_start: usize,
+}
+
+impl BpTree {
+	// Returns None unless `bits` is a balanced sequence of parentheses
+	pub fn new(bits: BitVector) -> Option<Self> {
+		let block_count = bits.len().div_ceil(BLOCK_BITS).max(1);
+		let leaf_start = block_count.next_power_of_two();
+		let mut min_tree = vec![isize::MAX; 2 * leaf_start];
+		let mut excess = 0;
+		min_tree[leaf_start] = 0;
+		for (i, bit) in bits.iter().enumerate() {
+			excess += if bit { 1 } else { -1 };
+			if excess < 0 { return None }
+
+			// Prefix length i + 1 is in block i / BLOCK_BITS, and also starts the next one
+			let leaf = &mut min_tree[leaf_start + i / BLOCK_BITS];
+			*leaf = (*leaf).min(excess);
+			if (i + 1).is_multiple_of(BLOCK_BITS) && i + 1 < bits.len() {
+				min_tree[leaf_start + (i + 1) / BLOCK_BITS] = excess;
+			}
+		}
+		if excess != 0 { return None }
+
+		for node in (1..leaf_start).rev() {
+			min_tree[node] = min_tree[2 * node].min(min_tree[2 * node + 1]);
+		}
+		Some(BpTree { bits: RankSelect::new(bits), min_tree, leaf_start })
+	}
+	// Builds the tree rooted at `root`, visiting each node's children in order.
+	// The traversal uses an explicit stack, so deep trees cannot overflow the call stack.
+	pub fn from_tree<N, C, I>(root: N, mut children: C) -> Self
+		where C: FnMut(&N) -> I, I: IntoIterator<Item=N>
+	{
+		let mut bits = BitVector::new();
+		let mut stack = vec![];
+		bits.push(true);
+		stack.push(children(&root).into_iter());
+		while let Some(siblings) = stack.last_mut() {
+			match siblings.next() {
+				Some(child) => {
+					bits.push(true);
+					let grandchildren = children(&child).into_iter();
+					stack.push(grandchildren);
+				},
+				None => {
+					bits.push(false);
+					stack.pop();
+				},
+			}
+		}
+		Self::new(bits).unwrap()
+	}
+
+	pub fn bits(&self) -> &BitVector {
+		self.bits.bits()
+	}
+	pub fn node_count(&self) -> usize {
+		self.bits.len() / 2
+	}
+	pub fn is_empty(&self) -> bool {
+		self.bits.is_empty()
+	}
+	pub fn root(&self) -> Option<usize> {
+		if self.is_empty() { None } else { Some(0) }
+	}
+	// The index of a node in preorder, and its inverse
+	pub fn preorder(&self, node: usize) -> Option<usize> {
+		self.bits.rank1(self.open_at(node)?)
+	}
+	pub fn node(&self, preorder: usize) -> Option<usize> {
+		self.bits.select1(preorder)
+	}
+
+	pub fn parent(&self, node: usize) -> Option<usize> {
+		let node = self.open_at(node)?;
+		self.backward_search(node, self.excess(node) - 1)
+	}
+	pub fn first_child(&self, node: usize) -> Option<usize> {
+		self.open_at(self.open_at(node)? + 1)
+	}
+	pub fn next_sibling(&self, node: usize) -> Option<usize> {
+		self.open_at(self.close(self.open_at(node)?) + 1)
+	}
+	pub fn is_leaf(&self, node: usize) -> Option<bool> {
+		let node = self.open_at(node)?;
+		Some(self.first_child(node).is_none())
+	}
+	// The number of nodes in the subtree rooted at `node`, including itself
+	pub fn subtree_size(&self, node: usize) -> Option<usize> {
+		let node = self.open_at(node)?;
+		Some((self.close(node) - node).div_ceil(2))
+	}
+	// The root has depth 0
+	pub fn depth(&self, node: usize) -> Option<usize> {
+		Some(self.excess(self.open_at(node)?) as usize)
+	}
+	pub fn is_ancestor(&self, ancestor: usize, node: usize) -> Option<bool> {
+		let (ancestor, node) = (self.open_at(ancestor)?, self.open_at(node)?);
+		Some(ancestor <= node && node < self.close(ancestor))
+	}
+	// The lowest common ancestor, or None if the nodes are in different trees of a forest
+	pub fn lca(&self, node1: usize, node2: usize) -> Option<usize> {
+		let (first, second) = (node1.min(node2), node1.max(node2));
+		if self.is_ancestor(first, second)? { return Some(first) }
+
+		// The subtree of the ancestor one level below the LCA ends between the nodes,
+		// so the lowest excess between them is the depth of the LCA + 1
+		let min_excess = self.min_excess(first, second);
+		self.backward_search(first + 1, min_excess - 1)
+	}
+
+	fn excess(&self, prefix_len: usize) -> isize {
+		2 * self.bits.rank1(prefix_len).unwrap() as isize - prefix_len as isize
+	}
+	fn open_at(&self, index: usize) -> Option<usize> {
+		if self.bits.bits().get(index)? { Some(index) } else { None }
+	}
+	// The index of the closing parenthesis matching the open at `node`,
+	// which must be an open parenthesis
+	fn close(&self, node: usize) -> usize {
+		let after_open = node + 1;
+		self.forward_search(after_open, self.excess(after_open) - 1).unwrap() - 1
+	}
+	fn block_end(&self, block: usize) -> usize {
+		((block + 1) * BLOCK_BITS).min(self.bits.len())
+	}
+	fn load_byte(&self, start: usize) -> usize {
+		self.bits.bits().load_word(start) & 0xFF
+	}
+	fn step(&self, index: usize) -> isize {
+		if self.bits.bits().get(index).unwrap() { 1 } else { -1 }
+	}
+
+	// The smallest prefix length after `start` whose excess is <= target
+	fn forward_search(&self, start: usize, target: isize) -> Option<usize> {
+		let block = start / BLOCK_BITS;
+		let found = self.scan_forward(start, self.block_end(block), target);
+		if found.is_some() { return found }
+
+		let mut block = block;
+		loop {
+			block = self.next_block(block, target)?;
+			let block_start = block * BLOCK_BITS;
+			let found = self.scan_forward(block_start, self.block_end(block), target);
+			if found.is_some() { return found }
+		}
+	}
+	// The largest prefix length before `end` whose excess is <= target
+	fn backward_search(&self, end: usize, target: isize) -> Option<usize> {
+		if end == 0 { return None }
+
+		let block = (end - 1) / BLOCK_BITS;
+		let found = self.scan_backward(end, block * BLOCK_BITS, target);
+		if found.is_some() { return found }
+
+		let mut block = block;
+		loop {
+			block = self.previous_block(block, target)?;
+			let block_end = self.block_end(block);
+			let found = self.scan_backward(block_end, block * BLOCK_BITS, target);
+			if found.is_some() { return found }
+		}
+	}
+	// The minimum excess of the prefix lengths in start..=end
+	fn min_excess(&self, start: usize, end: usize) -> isize {
+		let (start_block, end_block) = (start / BLOCK_BITS, end / BLOCK_BITS);
+		if start_block == end_block { return self.scan_min(start, end) }
+
+		let mut min = self.scan_min(start, self.block_end(start_block))
+			.min(self.scan_min(end_block * BLOCK_BITS, end));
+		// Combine the leaves strictly between the partial blocks
+		let (mut left, mut right) =
+			(self.leaf_start + start_block + 1, self.leaf_start + end_block);
+		while left < right {
+			if left % 2 == 1 {
+				min = min.min(self.min_tree[left]);
+				left += 1;
+			}
+			if right % 2 == 1 {
+				right -= 1;
+				min = min.min(self.min_tree[right]);
+			}
+			left /= 2;
+			right /= 2;
+		}
+		min
+	}
+
+	// Scans prefix lengths in (start, end], skipping bytes whose minimum is too high
+	fn scan_forward(&self, start: usize, end: usize, target: isize) -> Option<usize> {
+		let (total, forward_min, _) = &BYTE_TABLES;
+		let mut excess = self.excess(start);
+		let mut index = start;
+		while index < end {
+			if index.is_multiple_of(8) && index + 8 <= end {
+				let byte = self.load_byte(index);
+				if excess + forward_min[byte] as isize > target {
+					excess += total[byte] as isize;
+					index += 8;
+					continue
+				}
+			}
+			excess += self.step(index);
+			index += 1;
+			if excess <= target { return Some(index) }
+		}
+		None
+	}
+	// Scans prefix lengths in [start, end), from the end
+	fn scan_backward(&self, end: usize, start: usize, target: isize) -> Option<usize> {
+		let (total, _, backward_min) = &BYTE_TABLES;
+		let mut excess = self.excess(end);
+		let mut index = end;
+		while index > start {
+			if index.is_multiple_of(8) && index - 8 >= start {
+				let byte = self.load_byte(index - 8);
+				let byte_start_excess = excess - total[byte] as isize;
+				if byte_start_excess + backward_min[byte] as isize > target {
+					excess = byte_start_excess;
+					index -= 8;
+					continue
+				}
+			}
+			index -= 1;
+			excess -= self.step(index);
+			if excess <= target { return Some(index) }
+		}
+		None
+	}
+	// The minimum excess of the prefix lengths in start..=end
+	fn scan_min(&self, start: usize, end: usize) -> isize {
+		let (total, forward_min, _) = &BYTE_TABLES;
+		let mut excess = self.excess(start);
+		let mut min = excess;
+		let mut index = start;
+		while index < end {
+			if index.is_multiple_of(8) && index + 8 <= end {
+				let byte = self.load_byte(index);
+				min = min.min(excess + forward_min[byte] as isize);
+				excess += total[byte] as isize;
+				index += 8;
+			}
+			else {
+				excess += self.step(index);
+				min = min.min(excess);
+				index += 1;
+			}
+		}
+		min
+	}
+	// The first block after `block` whose minimum excess is <= target
+	fn next_block(&self, block: usize, target: isize) -> Option<usize> {
+		let mut node = self.leaf_start + block;
+		while node > 1 {
+			if node.is_multiple_of(2) && self.min_tree[node + 1] <= target {
+				return Some(self.descend(node + 1, target, true))
+			}
+			node /= 2;
+		}
+		None
+	}
+	// The last block before `block` whose minimum excess is <= target
+	fn previous_block(&self, block: usize, target: isize) -> Option<usize> {
+		let mut node = self.leaf_start + block;
+		while node > 1 {
+			if node % 2 == 1 && self.min_tree[node - 1] <= target {
+				return Some(self.descend(node - 1, target, false))
+			}
+			node /= 2;
+		}
+		None
+	}
+	// Finds the leftmost (or rightmost) leaf under `node` whose minimum is <= target
+	fn descend(&self, mut node: usize, target: isize, leftmost: bool) -> usize {
+		while node < self.leaf_start {
+			let (first, second) =
+				if leftmost { (2 * node, 2 * node + 1) } else { (2 * node + 1, 2 * node) };
+			node = if self.min_tree[first] <= target { first } else { second };
+		}
+		node - self.leaf_start
+	}
+}
+
+// Records a depth-first traversal one node at a time
+#[derive(Default)]
+pub struct BpTreeBuilder {
+	bits: BitVector,
+}
+
+impl BpTreeBuilder {
+	pub fn new() -> Self {
+		BpTreeBuilder::default()
+	}
+	pub fn open(&mut self) {
+		self.bits.push(true)
+	}
+	pub fn close(&mut self) {
+		self.bits.push(false)
+	}
+	// Returns None if some node was not closed, or closed twice
+	pub fn finish(self) -> Option<BpTree> {
+		BpTree::new(self.bits)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::FromIterator;
+
+	// A pointer-based tree to compare against, with nodes in preorder
+	struct Naive {
+		parents: Vec<Option<usize>>,
+		children: Vec<Vec<usize>>,
+		depths: Vec<usize>,
+		sizes: Vec<usize>,
+	}
+
+	impl Naive {
+		// A deterministic pseudo-random tree: each node's parent is an earlier node
+		fn new(node_count: usize, seed: usize) -> Self {
+			let mut parents = vec![None];
+			let mut children = vec![vec![]];
+			for node in 1..node_count {
+				// Bias toward recent nodes to make deep paths
+				let back = (node * 2_654_435_761 + seed) % 7 + (node * seed) % 3;
+				let parent = node.saturating_sub(1 + back.min(node - 1));
+				parents.push(Some(parent));
+				children.push(vec![]);
+				children[parent].push(node);
+			}
+			// Renumber in preorder
+			let mut order = vec![];
+			let mut stack = vec![0];
+			while let Some(node) = stack.pop() {
+				order.push(node);
+				stack.extend(children[node].iter().rev());
+			}
+			let mut preorder = vec![0; node_count];
+			for (index, &node) in order.iter().enumerate() { preorder[node] = index }
+			let parents: Vec<_> = order.iter()
+				.map(|&node| parents[node].map(|parent| preorder[parent]))
+				.collect();
+			let children = order.iter()
+				.map(|&node| children[node].iter().map(|&child| preorder[child]).collect())
+				.collect();
+			// Parents come before their children in preorder
+			let mut depths = vec![0; node_count];
+			for node in 1..node_count { depths[node] = depths[parents[node].unwrap()] + 1 }
+			let mut sizes = vec![1; node_count];
+			for node in (1..node_count).rev() { sizes[parents[node].unwrap()] += sizes[node] }
+			Naive { parents, children, depths, sizes }
+		}
+		fn lca(&self, mut node1: usize, mut node2: usize) -> usize {
+			while self.depths[node1] > self.depths[node2] { node1 = self.parents[node1].unwrap() }
+			while self.depths[node2] > self.depths[node1] { node2 = self.parents[node2].unwrap() }
+			while node1 != node2 {
+				node1 = self.parents[node1].unwrap();
+				node2 = self.parents[node2].unwrap();
+			}
+			node1
+		}
+	}
+
+	fn check_against_naive(naive: &Naive) {
+		let tree = BpTree::from_tree(0, |&node| naive.children[node].clone());
+		let node_count = naive.parents.len();
+		assert_eq!(tree.node_count(), node_count);
+		let nodes: Vec<_> = (0..node_count).map(|i| tree.node(i).unwrap()).collect();
+		for (i, &node) in nodes.iter().enumerate() {
+			assert_eq!(tree.preorder(node), Some(i));
+			assert_eq!(tree.parent(node), naive.parents[i].map(|parent| nodes[parent]));
+			let children = &naive.children[i];
+			assert_eq!(tree.first_child(node), children.first().map(|&child| nodes[child]));
+			for pair in children.windows(2) {
+				assert_eq!(tree.next_sibling(nodes[pair[0]]), Some(nodes[pair[1]]));
+			}
+			if let Some(&last) = children.last() { assert_eq!(tree.next_sibling(nodes[last]), None) }
+			assert_eq!(tree.subtree_size(node), Some(naive.sizes[i]));
+			assert_eq!(tree.depth(node), Some(naive.depths[i]));
+		}
+		assert_eq!(tree.next_sibling(nodes[0]), None);
+		for i in (0..node_count).step_by(node_count / 60 + 1) {
+			for j in (0..node_count).step_by(node_count / 40 + 1) {
+				assert_eq!(tree.lca(nodes[i], nodes[j]), Some(nodes[naive.lca(i, j)]));
+			}
+		}
+	}
+
+	#[test]
+	fn test_small() {
+		// (()(()()))
+		let bits = BitVector::from_iter(
+			[1, 1, 0, 1, 1, 0, 1, 0, 0, 0].iter().map(|&bit| bit == 1)
+		);
+		let tree = BpTree::new(bits).unwrap();
+		assert_eq!(tree.root(), Some(0));
+		assert_eq!(tree.node_count(), 5);
+		assert_eq!(tree.first_child(0), Some(1));
+		assert_eq!(tree.next_sibling(1), Some(3));
+		assert_eq!(tree.is_leaf(1), Some(true));
+		assert_eq!(tree.first_child(3), Some(4));
+		assert_eq!(tree.next_sibling(4), Some(6));
+		assert_eq!(tree.parent(6), Some(3));
+		assert_eq!(tree.parent(0), None);
+		assert_eq!(tree.subtree_size(3), Some(3));
+		assert_eq!(tree.depth(6), Some(2));
+		assert_eq!(tree.lca(1, 6), Some(0));
+		assert_eq!(tree.lca(4, 6), Some(3));
+		assert_eq!(tree.lca(3, 6), Some(3));
+		// Closing parentheses and out-of-range indices are not nodes
+		for &index in &[2, 9, 10, usize::MAX] {
+			assert_eq!(tree.preorder(index), None);
+			assert_eq!(tree.parent(index), None);
+			assert_eq!(tree.first_child(index), None);
+			assert_eq!(tree.next_sibling(index), None);
+			assert_eq!(tree.is_leaf(index), None);
+			assert_eq!(tree.subtree_size(index), None);
+			assert_eq!(tree.depth(index), None);
+			assert_eq!(tree.is_ancestor(0, index), None);
+			assert_eq!(tree.lca(index, 1), None);
+		}
+
+		assert!(BpTree::new(BitVector::from_iter(vec![true, false, false, true])).is_none());
+		assert!(BpTree::new(BitVector::from_iter(vec![true, true, false])).is_none());
+	}
+
+	#[test]
+	fn test_against_naive() {
+		for &(node_count, seed) in &[(1, 0), (2, 0), (100, 1), (1000, 2), (5000, 3)] {
+			check_against_naive(&Naive::new(node_count, seed));
+		}
+	}
+
+	#[test]
+	fn test_deep() {
+		// A path of 100,000 nodes, built without recursion
+		let tree = BpTree::from_tree(0, |&depth| if depth < 99_999 { Some(depth + 1) } else { None });
+		assert_eq!(tree.node_count(), 100_000);
+		let deepest = tree.node(99_999).unwrap();
+		assert_eq!(tree.depth(deepest), Some(99_999));
+		assert_eq!(tree.parent(deepest), Some(deepest - 1));
+		assert_eq!(tree.subtree_size(0), Some(100_000));
+		assert_eq!(tree.lca(deepest, 500), Some(500));
+
+		let mut builder = BpTreeBuilder::new();
+		for _ in 0..3 {
+			builder.open();
+			builder.close();
+		}
+		// A forest of 3 roots
+		let forest = builder.finish().unwrap();
+		assert_eq!(forest.next_sibling(2), Some(4));
+		assert_eq!(forest.lca(0, 4), None);
+	}
+}
@@ -0,0 +1,195 @@
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+use std::iter::FusedIterator;
+use std::mem;
+use super::{BitSlice, BitVector};
+
+const WORD_BYTES: usize = mem::size_of::<usize>();
+const LEN_BYTES: usize = mem::size_of::<u64>();
+
+// Which bit of each byte holds the earliest bit of the vector
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BitOrder {
+	LsbFirst,
+	MsbFirst,
+}
+
+impl BitSlice {
+	// Groups of 8 bits, least significant first, with the last byte zero-padded
+	pub fn bytes(&self) -> Bytes<'_> {
+		Bytes { bits: self, index: 0 }
+	}
+}
+
+impl BitVector {
+	// Returns None if `words` has fewer than `len` bits
+	pub fn from_words(words: Vec<usize>, len: usize) -> Option<Self> {
+		Self::from_storage(words, len)
+	}
+	// Equivalent to bytes().collect()
+	pub fn into_bytes(self) -> Vec<u8> {
+		let byte_len = self.len.div_ceil(8);
+		let words = self.into_words();
+		let mut bytes = Vec::with_capacity(words.len() * WORD_BYTES);
+		for word in words { bytes.extend_from_slice(&word.to_le_bytes()) }
+		bytes.truncate(byte_len);
+		bytes
+	}
+	// Inverse of bytes() when given BitOrder::LsbFirst.
+	// Returns None if `bytes` has fewer than `bit_len` bits.
+	pub fn from_bytes(bytes: &[u8], bit_len: usize, order: BitOrder) -> Option<Self> {
+		let byte_len = bit_len.div_ceil(8);
+		if byte_len > bytes.len() { return None }
+
+		let words = bytes[..byte_len].chunks(WORD_BYTES).map(|word_bytes| {
+			let mut le_bytes = [0; WORD_BYTES];
+			for (le_byte, &byte) in le_bytes.iter_mut().zip(word_bytes) {
+				*le_byte = match order {
+					BitOrder::LsbFirst => byte,
+					BitOrder::MsbFirst => byte.reverse_bits(),
+				}
+			}
+			usize::from_le_bytes(le_bytes)
+		}).collect();
+		let mut result = BitVector { len: bit_len, words };
+		result.clear_unused_bits();
+		Some(result)
+	}
+
+	// Writes the length as a little-endian u64, followed by bytes()
+	pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+		writer.write_all(&(self.len as u64).to_le_bytes())?;
+		let bytes: Vec<_> = self.bytes().collect();
+		writer.write_all(&bytes)
+	}
+	// Reads a vector written by write_to()
+	pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+		let mut len_bytes = [0; LEN_BYTES];
+		reader.read_exact(&mut len_bytes)?;
+		let len = u64::from_le_bytes(len_bytes);
+		let bit_len: usize = len.try_into().map_err(|_|
+			io::Error::new(io::ErrorKind::InvalidData, "BitVector is too long")
+		)?;
+		// Avoid allocating the whole claimed length up front,
+		// since the input may be truncated or corrupt
+		let byte_len = len.div_ceil(8);
+		let mut bytes = vec![];
+		reader.take(byte_len).read_to_end(&mut bytes)?;
+		Self::from_bytes(&bytes, bit_len, BitOrder::LsbFirst).ok_or_else(||
+			io::Error::new(io::ErrorKind::UnexpectedEof, "BitVector is truncated")
+		)
+	}
+}
+
+pub struct Bytes<'a> {
+	bits: &'a BitSlice,
+	index: usize,
+}
+
+impl Iterator for Bytes<'_> {
+	type Item = u8;
+
+	fn next(&mut self) -> Option<u8> {
+		if self.index >= self.bits.len() { return None }
+
+		let byte = self.bits.load_word(self.index) as u8;
+		self.index += 8;
+		Some(byte)
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.bits.len().saturating_sub(self.index).div_ceil(8);
+		(len, Some(len))
+	}
+}
+impl ExactSizeIterator for Bytes<'_> {}
+impl FusedIterator for Bytes<'_> {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::FromIterator;
+
+	fn pattern(len: usize) -> BitVector {
+		BitVector::from_iter((0..len).map(|i| (i * 7 + i / 5) % 3 == 0))
+	}
+
+	#[test]
+	fn test_from_bytes() {
+		for len in 0..300 {
+			let bits = pattern(len);
+			let bytes: Vec<_> = bits.bytes().collect();
+			assert_eq!(BitVector::from_bytes(&bytes, len, BitOrder::LsbFirst), Some(bits.clone()));
+			let reversed: Vec<_> = bytes.iter().map(|byte| byte.reverse_bits()).collect();
+			assert_eq!(BitVector::from_bytes(&reversed, len, BitOrder::MsbFirst), Some(bits));
+			if len % 8 > 0 {
+				assert_eq!(BitVector::from_bytes(&bytes, len + 8, BitOrder::LsbFirst), None)
+			}
+		}
+		let bits = BitVector::from_bytes(&[0b1100_0101, 0xFF], 6, BitOrder::MsbFirst).unwrap();
+		assert_eq!(bits, BitVector::from_iter(vec![true, true, false, false, false, true]));
+		let bits = BitVector::from_bytes(&[0b1100_0101, 0xFF], 6, BitOrder::LsbFirst).unwrap();
+		assert_eq!(bits, BitVector::from_iter(vec![true, false, true, false, false, false]));
+		// Extra bits must be ignored when comparing and exporting
+		let mut pushed = bits.clone();
+		pushed.push(false);
+		assert_eq!(pushed.bytes().collect::<Vec<_>>(), [0b0000_0101]);
+	}
+
+	#[test]
+	fn test_write_read() {
+		for len in 0..300 {
+			let bits = pattern(len);
+			let mut written = vec![];
+			bits.write_to(&mut written).unwrap();
+			assert_eq!(written.len(), 8 + len.div_ceil(8));
+			assert_eq!(BitVector::read_from(&written[..]).unwrap(), bits);
+
+			// Consecutive vectors can be read back from the same stream
+			pattern(len / 2).write_to(&mut written).unwrap();
+			let mut reader = &written[..];
+			assert_eq!(BitVector::read_from(&mut reader).unwrap(), bits);
+			assert_eq!(BitVector::read_from(&mut reader).unwrap(), pattern(len / 2));
+			assert!(reader.is_empty());
+		}
+	}
+
+	#[test]
+	fn test_words() {
+		for len in 0..300 {
+			let bits = pattern(len);
+			let bytes: Vec<_> = bits.bytes().collect();
+			assert_eq!(bits.bytes().len(), len.div_ceil(8));
+			assert_eq!(bits.clone().into_bytes(), bytes);
+			assert_eq!(bits.as_words().len(), len.div_ceil(WORD_BYTES * 8));
+			let words = bits.clone().into_words();
+			assert_eq!(BitVector::from_words(words.clone(), len), Some(bits.clone()));
+			assert_eq!(BitVector::from_words(words, len + WORD_BYTES * 8), None);
+		}
+		// Unused bits are zeroed when the words are taken
+		let mut bits = BitVector::from_iter(vec![true; 100]);
+		for _ in 0..97 { bits.pop(); }
+		assert_eq!(bits.as_words().len(), 1);
+		assert_eq!(bits.clone().into_words(), [0b111]);
+		assert_eq!(bits.into_bytes(), [0b111]);
+	}
+
+	#[test]
+	fn test_slice_bytes() {
+		let bits = pattern(200);
+		for start in 0..20 {
+			let slice = &bits[start..(start + 100)];
+			let expected: Vec<_> = slice.to_bit_vector().into_bytes();
+			assert!(slice.bytes().eq(expected));
+		}
+	}
+
+	#[test]
+	fn test_truncated() {
+		let mut written = vec![];
+		pattern(100).write_to(&mut written).unwrap();
+		for truncated_len in 0..written.len() {
+			let error = BitVector::read_from(&written[..truncated_len]).unwrap_err();
+			assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+		}
+	}
+}
@@ -0,0 +1,462 @@
+use std::ops::{BitAnd, BitOr, BitXor};
+use super::{BitVector, WORD_BITS};
+
+// Each run is some number of identical "fill" words (all zeros or all ones)
+// followed by the literal words up to the next run's literal_start.
+// Bits past the end of the vector are always zero.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Run {
+	start_word: usize,
+	fill: bool,
+	fill_words: usize,
+	literal_start: usize,
+}
+
+// A bitmap stored as runs of words, in the style of EWAH.
+// Long stretches of zeros or ones take a constant amount of space.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CompressedBitVector {
+	len: usize,
+	runs: Vec<Run>,
+	literals: Vec<usize>,
+}
+
+#[derive(Clone, Copy)]
+enum Chunk {
+	Fill(bool, usize),
+	Literal(usize),
+}
+
+// Appends words, merging identical fill words into runs
+struct Builder {
+	runs: Vec<Run>,
+	literals: Vec<usize>,
+	words: usize,
+}
+
+impl Builder {
+	fn new() -> Self {
+		Builder { runs: vec![], literals: vec![], words: 0 }
+	}
+
+	fn push_fill(&mut self, fill: bool, count: usize) {
+		if count == 0 { return }
+
+		let literal_start = self.literals.len();
+		match self.runs.last_mut() {
+			Some(run) if run.literal_start == literal_start &&
+				(run.fill == fill || run.fill_words == 0) => {
+				run.fill = fill;
+				run.fill_words += count;
+			},
+			_ => self.runs.push(
+				Run { start_word: self.words, fill, fill_words: count, literal_start }
+			),
+		}
+		self.words += count;
+	}
+	fn push_literal(&mut self, word: usize) {
+		if word == 0 || word == !0 { return self.push_fill(word != 0, 1) }
+
+		if self.runs.is_empty() {
+			self.runs.push(Run {
+				start_word: self.words,
+				fill: false,
+				fill_words: 0,
+				literal_start: self.literals.len(),
+			})
+		}
+		self.literals.push(word);
+		self.words += 1;
+	}
+	fn finish(self, len: usize) -> CompressedBitVector {
+		let Builder { runs, literals, .. } = self;
+		CompressedBitVector { len, runs, literals }
+	}
+}
+
+// Reads the words of a bitmap in order, continuing with zeros after the end
+struct Words<'a> {
+	bitmap: &'a CompressedBitVector,
+	run_index: usize,
+	fill_words: usize,
+	literal_index: usize,
+	literal_end: usize,
+}
+
+impl<'a> Words<'a> {
+	fn new(bitmap: &'a CompressedBitVector) -> Self {
+		let mut words =
+			Words { bitmap, run_index: 0, fill_words: 0, literal_index: 0, literal_end: 0 };
+		words.next_run();
+		words
+	}
+
+	fn next_run(&mut self) {
+		let runs = &self.bitmap.runs;
+		while self.fill_words == 0 && self.literal_index == self.literal_end &&
+			self.run_index < runs.len()
+		{
+			let run = &runs[self.run_index];
+			self.run_index += 1;
+			self.fill_words = run.fill_words;
+			self.literal_index = run.literal_start;
+			self.literal_end = runs.get(self.run_index)
+				.map_or(self.bitmap.literals.len(), |run| run.literal_start);
+		}
+	}
+	fn peek(&self) -> Chunk {
+		if self.fill_words > 0 {
+			Chunk::Fill(self.bitmap.runs[self.run_index - 1].fill, self.fill_words)
+		}
+		else if self.literal_index < self.literal_end {
+			Chunk::Literal(self.bitmap.literals[self.literal_index])
+		}
+		else { Chunk::Fill(false, usize::MAX) }
+	}
+	fn skip(&mut self, mut count: usize) {
+		while count > 0 {
+			let skipped =
+				if self.fill_words > 0 {
+					let skipped = count.min(self.fill_words);
+					self.fill_words -= skipped;
+					skipped
+				}
+				else if self.literal_index < self.literal_end {
+					let skipped = count.min(self.literal_end - self.literal_index);
+					self.literal_index += skipped;
+					skipped
+				}
+				else { return };
+			count -= skipped;
+			self.next_run();
+		}
+	}
+}
+
+impl CompressedBitVector {
+	pub fn new() -> Self {
+		CompressedBitVector { len: 0, runs: vec![], literals: vec![] }
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	pub fn get(&self, index: usize) -> Option<bool> {
+		if index >= self.len { return None }
+
+		let word_index = BitVector::to_word_index(index);
+		let run_index = self.runs.partition_point(|run| run.start_word <= word_index);
+		if run_index == 0 { return Some(false) }
+
+		let run = &self.runs[run_index - 1];
+		let word_in_run = word_index - run.start_word;
+		let word =
+			if word_in_run < run.fill_words { BitVector::fill_word(run.fill) }
+			else {
+				let literal_index = run.literal_start + (word_in_run - run.fill_words);
+				let literal_end = self.runs.get(run_index)
+					.map_or(self.literals.len(), |run| run.literal_start);
+				if literal_index < literal_end { self.literals[literal_index] } else { 0 }
+			};
+		Some(word >> BitVector::to_word_offset(index) & 1 > 0)
+	}
+	pub fn count_ones(&self) -> usize {
+		let fill_ones: usize = self.runs.iter()
+			.filter(|run| run.fill)
+			.map(|run| BitVector::from_word_index(run.fill_words))
+			.sum();
+		let literal_ones: usize =
+			self.literals.iter().map(|word| word.count_ones() as usize).sum();
+		fill_ones + literal_ones
+	}
+	pub fn count_zeros(&self) -> usize {
+		self.len - self.count_ones()
+	}
+	pub fn iter(&self) -> Iter<'_> {
+		Iter { words: Words::new(self), word: 0, index: 0, len: self.len }
+	}
+	// Indices of the ones, skipping over fills of zeros
+	pub fn iter_ones(&self) -> IterOnes<'_> {
+		IterOnes { words: Words::new(self), word: 0, word_start: 0, next_word_start: 0 }
+	}
+	pub fn to_bit_vector(&self) -> BitVector {
+		let word_count = BitVector::to_words_ceil(self.len);
+		let mut words = Vec::with_capacity(word_count);
+		let mut stream = Words::new(self);
+		while words.len() < word_count {
+			match stream.peek() {
+				Chunk::Fill(fill, count) => {
+					let count = count.min(word_count - words.len());
+					words.resize(words.len() + count, BitVector::fill_word(fill));
+					stream.skip(count);
+				},
+				Chunk::Literal(word) => {
+					words.push(word);
+					stream.skip(1);
+				},
+			}
+		}
+		BitVector { len: self.len, words }
+	}
+
+	// Combines the words of two bitmaps, zero-extending the shorter one.
+	// When one side is a fill that determines the result on its own,
+	// the other side's words are skipped without being examined.
+	fn combine<F: Fn(usize, usize) -> usize>(&self, other: &Self, op: F) -> Self {
+		use Chunk::*;
+
+		let len = self.len.max(other.len);
+		let word_count = BitVector::to_words_ceil(len);
+		let fill_word = BitVector::fill_word;
+		// The fill that results from `fill` and any other word, if there is one
+		let fill_result = |fill: bool, fill_first: bool| {
+			let apply = |word|
+				if fill_first { op(fill_word(fill), word) } else { op(word, fill_word(fill)) };
+			let result = apply(0);
+			if result == apply(!0) && (result == 0 || result == !0) { Some(result != 0) }
+			else { None }
+		};
+		let mut builder = Builder::new();
+		let (mut these_words, mut other_words) = (Words::new(self), Words::new(other));
+		while builder.words < word_count {
+			let remaining = word_count - builder.words;
+			let count = match (these_words.peek(), other_words.peek()) {
+				(Fill(this_fill, this_count), Fill(other_fill, other_count)) => {
+					let count = this_count.min(other_count).min(remaining);
+					let result = op(fill_word(this_fill), fill_word(other_fill));
+					builder.push_fill(result != 0, count);
+					count
+				},
+				(Fill(fill, count), Literal(word)) => match fill_result(fill, true) {
+					Some(result) => {
+						let count = count.min(remaining);
+						builder.push_fill(result, count);
+						count
+					},
+					None => {
+						builder.push_literal(op(fill_word(fill), word));
+						1
+					},
+				},
+				(Literal(word), Fill(fill, count)) => match fill_result(fill, false) {
+					Some(result) => {
+						let count = count.min(remaining);
+						builder.push_fill(result, count);
+						count
+					},
+					None => {
+						builder.push_literal(op(word, fill_word(fill)));
+						1
+					},
+				},
+				(Literal(this_word), Literal(other_word)) => {
+					builder.push_literal(op(this_word, other_word));
+					1
+				},
+			};
+			these_words.skip(count);
+			other_words.skip(count);
+		}
+		builder.finish(len)
+	}
+}
+
+impl Default for CompressedBitVector {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl From<&BitVector> for CompressedBitVector {
+	fn from(bits: &BitVector) -> Self {
+		let word_count = BitVector::to_words_ceil(bits.len);
+		let extra_bits = BitVector::to_word_offset(bits.len);
+		let mut builder = Builder::new();
+		for (index, &word) in bits.words[..word_count].iter().enumerate() {
+			let word =
+				if index + 1 == word_count && extra_bits > 0 { word & ((1 << extra_bits) - 1) }
+				else { word };
+			builder.push_literal(word);
+		}
+		builder.finish(bits.len)
+	}
+}
+impl From<&CompressedBitVector> for BitVector {
+	fn from(bits: &CompressedBitVector) -> Self {
+		bits.to_bit_vector()
+	}
+}
+
+// As with BitVector, the shorter operand is treated as padded with zeros
+macro_rules! compressed_op {
+	($op:ident, $op_fn:ident, $word_op:expr) => {
+		impl $op<&CompressedBitVector> for &CompressedBitVector {
+			type Output = CompressedBitVector;
+
+			fn $op_fn(self, other: &CompressedBitVector) -> CompressedBitVector {
+				self.combine(other, $word_op)
+			}
+		}
+		impl $op<CompressedBitVector> for CompressedBitVector {
+			type Output = CompressedBitVector;
+
+			fn $op_fn(self, other: CompressedBitVector) -> CompressedBitVector {
+				self.combine(&other, $word_op)
+			}
+		}
+	};
+}
+
+compressed_op!(BitAnd, bitand, |a, b| a & b);
+compressed_op!(BitOr, bitor, |a, b| a | b);
+compressed_op!(BitXor, bitxor, |a, b| a ^ b);
+
+pub struct Iter<'a> {
+	words: Words<'a>,
+	word: usize,
+	index: usize,
+	len: usize,
+}
+
+impl Iterator for Iter<'_> {
+	type Item = bool;
+
+	fn next(&mut self) -> Option<bool> {
+		if self.index == self.len { return None }
+
+		let word_offset = BitVector::to_word_offset(self.index);
+		if word_offset == 0 {
+			self.word = match self.words.peek() {
+				Chunk::Fill(fill, _) => BitVector::fill_word(fill),
+				Chunk::Literal(word) => word,
+			};
+			self.words.skip(1);
+		}
+		self.index += 1;
+		Some(self.word >> word_offset & 1 > 0)
+	}
+}
+
+pub struct IterOnes<'a> {
+	words: Words<'a>,
+	word: usize,
+	word_start: usize,
+	next_word_start: usize,
+}
+
+impl Iterator for IterOnes<'_> {
+	type Item = usize;
+
+	fn next(&mut self) -> Option<usize> {
+		loop {
+			if self.word != 0 {
+				let offset = self.word.trailing_zeros() as usize;
+				self.word &= self.word - 1;
+				return Some(self.word_start + offset)
+			}
+
+			match self.words.peek() {
+				// Past the last run
+				Chunk::Fill(false, usize::MAX) => return None,
+				Chunk::Fill(false, count) => {
+					self.next_word_start += BitVector::from_word_index(count);
+					self.words.skip(count);
+				},
+				Chunk::Fill(true, _) => {
+					self.word = !0;
+					self.word_start = self.next_word_start;
+					self.next_word_start += WORD_BITS;
+					self.words.skip(1);
+				},
+				Chunk::Literal(word) => {
+					self.word = word;
+					self.word_start = self.next_word_start;
+					self.next_word_start += WORD_BITS;
+					self.words.skip(1);
+				},
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::FromIterator;
+
+	// Mixes long runs of zeros and ones with scattered bits
+	fn runs_pattern(len: usize, seed: usize) -> BitVector {
+		BitVector::from_iter((0..len).map(|i| {
+			let block = (i + seed * 37) / 300;
+			match block % 4 {
+				0 => false,
+				1 => true,
+				2 => (i * 7 + seed).is_multiple_of(11),
+				_ => (i * i + seed).is_multiple_of(3),
+			}
+		}))
+	}
+
+	#[test]
+	fn test_round_trip() {
+		for len in (0..2000).step_by(37) {
+			for seed in 0..4 {
+				let bits = runs_pattern(len, seed);
+				let compressed = CompressedBitVector::from(&bits);
+				assert_eq!(compressed.len(), len);
+				assert_eq!(compressed.to_bit_vector(), bits);
+				assert!(compressed.iter().eq(&bits));
+				assert!(compressed.iter_ones().eq(bits.iter_ones()));
+				assert_eq!(compressed.count_ones(), bits.count_ones());
+				assert_eq!(compressed.count_zeros(), bits.count_zeros());
+				for i in 0..(len + 10) { assert_eq!(compressed.get(i), bits.get(i)) }
+			}
+		}
+	}
+
+	#[test]
+	fn test_ops() {
+		let lengths = [0, 1, 64, 65, 700, 1500, 3000];
+		for &a_len in &lengths {
+			for &b_len in &lengths {
+				let a = runs_pattern(a_len, 1);
+				let b = runs_pattern(b_len, 2);
+				let (compressed_a, compressed_b) =
+					(CompressedBitVector::from(&a), CompressedBitVector::from(&b));
+				let and = &compressed_a & &compressed_b;
+				assert_eq!(and.to_bit_vector(), &a & &b);
+				assert_eq!(and, CompressedBitVector::from(&(&a & &b)));
+				let or = &compressed_a | &compressed_b;
+				assert_eq!(or.to_bit_vector(), &a | &b);
+				assert_eq!(or, CompressedBitVector::from(&(&a | &b)));
+				let xor = compressed_a ^ compressed_b;
+				assert_eq!(xor.to_bit_vector(), &a ^ &b);
+				assert_eq!(xor, CompressedBitVector::from(&(&a ^ &b)));
+			}
+		}
+	}
+
+	#[test]
+	fn test_compression() {
+		let mut bits = BitVector::from_iter(vec![false; 1_000_000]);
+		bits.set(500_000, true);
+		bits.extend(vec![true; 1_000_000]);
+		let compressed = CompressedBitVector::from(&bits);
+		// Zeros and one literal, zeros, then ones
+		assert_eq!(compressed.runs.len(), 3);
+		assert_eq!(compressed.literals.len(), 1);
+		assert_eq!(compressed.count_ones(), 1_000_001);
+		assert!(compressed.iter_ones().take(2).eq(vec![500_000, 1_000_000]));
+		assert_eq!(compressed.get(500_000), Some(true));
+		assert_eq!(compressed.get(1_500_000), Some(true));
+
+		let first_half = CompressedBitVector::from(&BitVector::from_iter(vec![true; 1_000_000]));
+		let intersection = &compressed & &first_half;
+		assert_eq!(intersection.count_ones(), 1);
+		assert_eq!(intersection.len(), 2_000_000);
+	}
+}
@@ -0,0 +1,156 @@
+use std::iter::{self, FromIterator};
+use std::ops::RangeBounds;
+use super::{range_bounds, BitSlice, BitVector, IntoIter};
+
+impl BitVector {
+	pub fn insert(&mut self, index: usize, value: bool) -> Option<()> {
+		if index > self.len { return None }
+
+		let len = self.len;
+		self.push(value);
+		self.move_bits(index, index + 1, len - index);
+		unsafe { self.set_unchecked(index, value) }
+		Some(())
+	}
+	pub fn remove(&mut self, index: usize) -> Option<bool> {
+		let value = self.get(index)?;
+		let len = self.len;
+		self.move_bits(index + 1, index, len - 1 - index);
+		self.len -= 1;
+		Some(value)
+	}
+	pub fn truncate(&mut self, len: usize) {
+		if len < self.len { self.len = len }
+	}
+	pub fn split_off(&mut self, at: usize) -> Self {
+		assert!(at <= self.len, "Split index out of bounds");
+		let tail = self[at..].to_bit_vector();
+		self.len = at;
+		tail
+	}
+	pub fn append(&mut self, other: &mut Self) {
+		let len = self.len;
+		self.replace_range(len, len, other);
+		other.clear();
+	}
+	// Unlike Vec::drain(), the bits are removed immediately
+	pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> IntoIter {
+		self.splice(range, iter::empty())
+	}
+	pub fn splice<R, I>(&mut self, range: R, replacement: I) -> IntoIter
+		where R: RangeBounds<usize>, I: IntoIterator<Item=bool>
+	{
+		let (start, end) = range_bounds(range, self.len).expect("Range out of bounds");
+		let removed = self[start..end].to_bit_vector();
+		self.replace_range(start, end, &BitVector::from_iter(replacement));
+		removed.into_iter()
+	}
+
+	// Replaces bits[start..end] with `replacement`, shifting the bits after it
+	fn replace_range(&mut self, start: usize, end: usize, replacement: &BitSlice) {
+		let old_len = self.len;
+		let new_end = start + replacement.len();
+		let new_len = new_end + (old_len - end);
+		let word_count = Self::to_words_ceil(new_len);
+		if word_count > self.words.len() { self.words.resize(word_count, 0) }
+		self.len = old_len.max(new_len);
+		self.move_bits(end, new_end, old_len - end);
+		self.len = new_len;
+		self.store_bits(start, replacement);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pattern(len: usize) -> Vec<bool> {
+		(0..len).map(|i| (i * i + 3 * i) % 7 < 3).collect()
+	}
+
+	fn check_equal(bits: &BitVector, values: &[bool]) {
+		assert_eq!(bits.len(), values.len());
+		assert!(bits.iter().eq(values.iter().cloned()));
+		assert_eq!(*bits, BitVector::from_iter(values));
+	}
+
+	#[test]
+	fn test_insert_remove() {
+		for len in [0, 1, 63, 64, 65, 200].iter().cloned() {
+			for index in 0..=len {
+				for &value in &[false, true] {
+					let mut values = pattern(len);
+					let mut bits = BitVector::from_iter(&values);
+					assert_eq!(bits.insert(index, value), Some(()));
+					values.insert(index, value);
+					check_equal(&bits, &values);
+					assert_eq!(bits.remove(index), Some(value));
+					values.remove(index);
+					check_equal(&bits, &values);
+					if index < len {
+						assert_eq!(bits.remove(index), Some(values.remove(index)));
+						check_equal(&bits, &values);
+					}
+				}
+			}
+			let mut bits = BitVector::from_iter(pattern(len));
+			assert_eq!(bits.insert(len + 1, true), None);
+			assert_eq!(bits.remove(len), None);
+		}
+	}
+
+	#[test]
+	fn test_truncate_split_append() {
+		for len in [0, 1, 63, 64, 65, 200].iter().cloned() {
+			let values = pattern(len);
+			for at in 0..=len {
+				let mut bits = BitVector::from_iter(&values);
+				let mut tail = bits.split_off(at);
+				check_equal(&bits, &values[..at]);
+				check_equal(&tail, &values[at..]);
+				bits.append(&mut tail);
+				check_equal(&bits, &values);
+				assert!(tail.is_empty());
+				bits.truncate(at);
+				check_equal(&bits, &values[..at]);
+				bits.truncate(len);
+				check_equal(&bits, &values[..at]);
+			}
+		}
+	}
+
+	#[test]
+	fn test_drain_splice() {
+		let values = pattern(150);
+		for start in (0..150).step_by(7) {
+			for end in (start..150).step_by(11) {
+				let mut bits = BitVector::from_iter(&values);
+				let mut expected = values.clone();
+				assert!(bits.drain(start..end).eq(expected.drain(start..end)));
+				check_equal(&bits, &expected);
+
+				for &replacement_len in &[0, 3, 64, 100] {
+					let replacement = pattern(replacement_len + 5)[5..].to_vec();
+					let mut bits = BitVector::from_iter(&values);
+					let mut expected = values.clone();
+					let removed = bits.splice(start..=end.min(149), replacement.clone());
+					assert!(removed.eq(expected.splice(start..=end.min(149), replacement)));
+					check_equal(&bits, &expected);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_unused_bits() {
+		// Growing into popped bits must not expose their old values
+		let mut bits = BitVector::from_iter(vec![true; 128]);
+		for _ in 0..100 { bits.pop(); }
+		bits.insert(0, false);
+		bits.splice(29..29, vec![false; 40]);
+		let mut expected = vec![false];
+		expected.extend(vec![true; 28]);
+		expected.extend(vec![false; 40]);
+		check_equal(&bits, &expected);
+	}
+}
@@ -0,0 +1,145 @@
+use super::{BitVector, IntVector, RankSelect, WORD_BITS};
+
+// A nondecreasing sequence of integers, each split into `low_bits` low bits,
+// stored packed, and its remaining high bits, stored in unary in `highs`:
+// value i sets bit (value >> low_bits) + i. This takes at most
+// 2 + log2(universe / len) bits per value, plus the select index on `highs`.
+pub struct EliasFano {
+	len: usize,
+	low_bits: usize,
+	lows: IntVector,
+	highs: RankSelect,
+}
+
+impl EliasFano {
+	// Returns None if `values` is not sorted
+	pub fn new(values: &[usize]) -> Option<Self> {
+		if values.windows(2).any(|pair| pair[0] > pair[1]) { return None }
+
+		let len = values.len();
+		let max = values.last().cloned().unwrap_or(0);
+		let low_bits = match max / len.max(1) {
+			0 => 0,
+			ratio => WORD_BITS - 1 - ratio.leading_zeros() as usize,
+		};
+		let mut lows = IntVector::with_capacity(low_bits, len);
+		let high_len = len + (max >> low_bits) + 1;
+		let mut highs = BitVector::from_storage(vec![0; high_len.div_ceil(WORD_BITS)], high_len)
+			.unwrap();
+		for (i, &value) in values.iter().enumerate() {
+			lows.push(value & ((1 << low_bits) - 1));
+			highs.set((value >> low_bits) + i, true);
+		}
+		Some(EliasFano { len, low_bits, lows, highs: RankSelect::new(highs) })
+	}
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	// The total size of the encoding, including the select index
+	pub fn size_in_bits(&self) -> usize {
+		self.lows.bits().len() + self.highs.size_in_bits()
+	}
+	pub fn get(&self, index: usize) -> Option<usize> {
+		let high_position = self.highs.select1(index)?;
+		Some(self.value(index, high_position))
+	}
+	// The smallest value that is at least `target`
+	pub fn successor(&self, target: usize) -> Option<usize> {
+		let high = target >> self.low_bits;
+		// The number of values with smaller high bits
+		let start = if high == 0 { 0 } else { self.highs.select0(high - 1)? + 1 - high };
+		self.iter_from(start).find(|&value| value >= target)
+	}
+	// The largest value that is at most `target`
+	pub fn predecessor(&self, target: usize) -> Option<usize> {
+		let high = target >> self.low_bits;
+		// The number of values with at most the same high bits
+		let end = match self.highs.select0(high) {
+			Some(bucket_end) => bucket_end - high,
+			None => self.len,
+		};
+		(0..end).rev().map(|index| self.get(index).unwrap()).find(|&value| value <= target)
+	}
+	pub fn iter(&self) -> Iter<'_> {
+		self.iter_from(0)
+	}
+
+	fn value(&self, index: usize, high_position: usize) -> usize {
+		(high_position - index) << self.low_bits | self.lows.get(index).unwrap()
+	}
+	fn iter_from(&self, index: usize) -> Iter<'_> {
+		let high_position = self.highs.select1(index).unwrap_or(self.highs.len());
+		Iter { sequence: self, index, high_position }
+	}
+}
+
+pub struct Iter<'a> {
+	sequence: &'a EliasFano,
+	index: usize,
+	high_position: usize,
+}
+
+impl Iterator for Iter<'_> {
+	type Item = usize;
+
+	fn next(&mut self) -> Option<usize> {
+		if self.index == self.sequence.len { return None }
+
+		let high_position = self.sequence.highs.bits().next_one(self.high_position)?;
+		let value = self.sequence.value(self.index, high_position);
+		self.index += 1;
+		self.high_position = high_position + 1;
+		Some(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn check_against_values(values: &[usize]) {
+		let sequence = EliasFano::new(values).unwrap();
+		assert_eq!(sequence.len(), values.len());
+		assert!(sequence.iter().eq(values.iter().cloned()));
+		for (i, &value) in values.iter().enumerate() { assert_eq!(sequence.get(i), Some(value)) }
+		assert_eq!(sequence.get(values.len()), None);
+		let max = values.last().cloned().unwrap_or(0);
+		for target in (0..max + 3).step_by(max / 500 + 1) {
+			let successor = values.iter().cloned().find(|&value| value >= target);
+			assert_eq!(sequence.successor(target), successor);
+			let predecessor = values.iter().rev().cloned().find(|&value| value <= target);
+			assert_eq!(sequence.predecessor(target), predecessor);
+		}
+		for &value in values {
+			assert_eq!(sequence.successor(value), Some(value));
+			assert_eq!(sequence.predecessor(value), Some(value));
+		}
+	}
+
+	#[test]
+	fn test_sequences() {
+		check_against_values(&[]);
+		check_against_values(&[0]);
+		check_against_values(&[7]);
+		check_against_values(&[0, 0, 0, 1, 1, 5, 5, 5]);
+		check_against_values(&(0..1000).collect::<Vec<_>>());
+		check_against_values(&(0..1000).map(|i| i * i).collect::<Vec<_>>());
+		check_against_values(&(0..1000).map(|i| i * 1_000_003 + i % 7).collect::<Vec<_>>());
+		check_against_values(&[3, 1 << 40, (1 << 40) + 1, usize::MAX >> 1]);
+		assert!(EliasFano::new(&[1, 2, 1]).is_none());
+	}
+
+	#[test]
+	fn test_size() {
+		// 100,000 values below 2^32 need about 2 + log2(2^32 / 100,000) bits each
+		let values: Vec<_> = (0..100_000).map(|i| i * 42_949 + i % 1000).collect();
+		let sequence = EliasFano::new(&values).unwrap();
+		assert_eq!(sequence.low_bits, 15);
+		let bits_per_value = sequence.size_in_bits() as f64 / values.len() as f64;
+		assert!(bits_per_value < 18.0, "{} bits per value", bits_per_value);
+		assert!(sequence.iter().eq(values));
+	}
+}
@@ -0,0 +1,215 @@
+use super::{BitVector, WORD_BITS};
+
+// A set of integers in 0..len, stored as a bit vector with summary levels above it.
+// Bit i of level l + 1 is set if word i of level l is non-zero,
+// and the top level has at most one word, so searches visit one word per level.
+#[derive(Clone, Debug)]
+pub struct HierarchicalBitSet {
+	len: usize,
+	levels: Vec<Vec<usize>>,
+}
+
+impl HierarchicalBitSet {
+	pub fn new(len: usize) -> Self {
+		let mut levels = vec![vec![0; len.div_ceil(WORD_BITS)]];
+		while levels.last().unwrap().len() > 1 {
+			let word_count = levels.last().unwrap().len().div_ceil(WORD_BITS);
+			levels.push(vec![0; word_count]);
+		}
+		HierarchicalBitSet { len, levels }
+	}
+	// One more than the largest value the set can hold
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	pub fn is_empty(&self) -> bool {
+		self.levels.last().unwrap().iter().all(|&word| word == 0)
+	}
+	pub fn contains(&self, value: usize) -> bool {
+		value < self.len && self.levels[0][value / WORD_BITS] >> (value % WORD_BITS) & 1 != 0
+	}
+	// Returns whether the value was newly added, or None if value >= len
+	pub fn insert(&mut self, value: usize) -> Option<bool> {
+		if value >= self.len { return None }
+		if self.contains(value) { return Some(false) }
+
+		// Set the bit in each level until reaching a word that was already non-empty
+		let mut index = value;
+		for level in &mut self.levels {
+			let word = &mut level[index / WORD_BITS];
+			let was_empty = *word == 0;
+			*word |= 1 << (index % WORD_BITS);
+			if !was_empty { break }
+			index /= WORD_BITS;
+		}
+		Some(true)
+	}
+	// Returns whether the value was present, or None if value >= len
+	pub fn remove(&mut self, value: usize) -> Option<bool> {
+		if value >= self.len { return None }
+		if !self.contains(value) { return Some(false) }
+
+		// Clear the bit in each level until reaching a word that is still non-empty
+		let mut index = value;
+		for level in &mut self.levels {
+			let word = &mut level[index / WORD_BITS];
+			*word &= !(1 << (index % WORD_BITS));
+			if *word != 0 { break }
+			index /= WORD_BITS;
+		}
+		Some(true)
+	}
+	pub fn clear(&mut self) {
+		for level in &mut self.levels {
+			for word in level { *word = 0 }
+		}
+	}
+	// The smallest value in the set that is at least `from`
+	pub fn next_set(&self, from: usize) -> Option<usize> {
+		if from >= self.len { return None }
+
+		self.next_in_level(0, from)
+	}
+	// The largest value in the set that is at most `from`
+	pub fn prev_set(&self, from: usize) -> Option<usize> {
+		if self.len == 0 { return None }
+
+		self.prev_in_level(0, from.min(self.len - 1))
+	}
+	pub fn first(&self) -> Option<usize> {
+		self.next_set(0)
+	}
+	pub fn last(&self) -> Option<usize> {
+		self.prev_set(usize::MAX)
+	}
+	pub fn iter(&self) -> Iter<'_> {
+		Iter { set: self, next: 0 }
+	}
+	pub fn to_bit_vector(&self) -> BitVector {
+		BitVector { len: self.len, words: self.levels[0].clone() }
+	}
+
+	fn next_in_level(&self, level: usize, from: usize) -> Option<usize> {
+		let words = &self.levels[level];
+		let word_index = from / WORD_BITS;
+		let word = *words.get(word_index)? & !0 << (from % WORD_BITS);
+		if word != 0 { return Some(word_index * WORD_BITS + word.trailing_zeros() as usize) }
+		if level + 1 == self.levels.len() { return None }
+
+		let next_index = self.next_in_level(level + 1, word_index + 1)?;
+		Some(next_index * WORD_BITS + words[next_index].trailing_zeros() as usize)
+	}
+	fn prev_in_level(&self, level: usize, from: usize) -> Option<usize> {
+		let words = &self.levels[level];
+		let word_index = from / WORD_BITS;
+		let word = words[word_index] & !0 >> (WORD_BITS - 1 - from % WORD_BITS);
+		if word != 0 { return Some(word_index * WORD_BITS + last_bit(word)) }
+		if word_index == 0 { return None }
+
+		let prev_index = self.prev_in_level(level + 1, word_index - 1)?;
+		Some(prev_index * WORD_BITS + last_bit(words[prev_index]))
+	}
+}
+
+fn last_bit(word: usize) -> usize {
+	WORD_BITS - 1 - word.leading_zeros() as usize
+}
+
+impl From<&BitVector> for HierarchicalBitSet {
+	fn from(bits: &BitVector) -> Self {
+		let mut set = HierarchicalBitSet::new(bits.len());
+		for value in bits.iter_ones() { set.insert(value); }
+		set
+	}
+}
+
+pub struct Iter<'a> {
+	set: &'a HierarchicalBitSet,
+	next: usize,
+}
+
+impl Iterator for Iter<'_> {
+	type Item = usize;
+
+	fn next(&mut self) -> Option<usize> {
+		let value = self.set.next_set(self.next)?;
+		self.next = value + 1;
+		Some(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::FromIterator;
+
+	fn check_against_scan(set: &HierarchicalBitSet, values: &[bool]) {
+		assert_eq!(set.len(), values.len());
+		assert_eq!(set.is_empty(), !values.contains(&true));
+		assert_eq!(set.to_bit_vector(), BitVector::from_iter(values));
+		assert!(set.iter().eq((0..values.len()).filter(|&i| values[i])));
+		let mut prev = None;
+		for (from, &value) in values.iter().enumerate() {
+			if value { prev = Some(from) }
+			assert_eq!(set.prev_set(from), prev);
+		}
+		assert_eq!(set.prev_set(values.len()), prev);
+		let mut next = None;
+		assert_eq!(set.next_set(values.len()), None);
+		for from in (0..values.len()).rev() {
+			if values[from] { next = Some(from) }
+			assert_eq!(set.next_set(from), next);
+		}
+	}
+
+	#[test]
+	fn test_insert_remove() {
+		for &len in &[0, 1, 63, 64, 65, 4095, 4096, 4097, 10_000] {
+			let mut set = HierarchicalBitSet::new(len);
+			let mut values = vec![false; len];
+			check_against_scan(&set, &values);
+			for i in (0..len).step_by(97) {
+				assert_eq!(set.insert(i), Some(true));
+				assert_eq!(set.insert(i), Some(false));
+				values[i] = true;
+			}
+			check_against_scan(&set, &values);
+			for i in (0..len).step_by(194) {
+				assert_eq!(set.remove(i), Some(true));
+				assert_eq!(set.remove(i), Some(false));
+				values[i] = false;
+			}
+			check_against_scan(&set, &values);
+			assert_eq!(set.insert(len), None);
+			assert_eq!(set.remove(len), None);
+			assert!(!set.contains(len));
+			set.clear();
+			assert!(set.is_empty());
+		}
+	}
+
+	#[test]
+	fn test_shared_words() {
+		// Removing one of several values in a word must keep the summaries set
+		let mut set = HierarchicalBitSet::new(1 << 20);
+		for &i in &[5, 6, 70_000, 70_001, 999_999] { set.insert(i); }
+		set.remove(5);
+		set.remove(70_000);
+		assert!(set.iter().eq(vec![6, 70_001, 999_999]));
+		assert_eq!(set.next_set(7), Some(70_001));
+		assert_eq!(set.prev_set(70_000), Some(6));
+		assert_eq!(set.first(), Some(6));
+		assert_eq!(set.last(), Some(999_999));
+		set.remove(999_999);
+		assert_eq!(set.last(), Some(70_001));
+		assert_eq!(set.next_set(70_002), None);
+	}
+
+	#[test]
+	fn test_from_bit_vector() {
+		let bits = BitVector::from_iter((0..5000).map(|i| (i * i) % 301 == 7));
+		let set = HierarchicalBitSet::from(&bits);
+		let values: Vec<_> = bits.iter().collect();
+		check_against_scan(&set, &values);
+	}
+}
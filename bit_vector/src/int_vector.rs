@@ -0,0 +1,214 @@
+use std::fmt::{self, Debug, Formatter};
+use std::iter::{FromIterator, FusedIterator};
+use super::{BitVector, Word, WORD_BITS};
+
+// The number of bits needed to store `value`
+fn min_width(value: usize) -> usize {
+	WORD_BITS - value.leading_zeros() as usize
+}
+
+// Integers of `width` bits each, packed into a BitVector.
+// Element i occupies bits [i * width, (i + 1) * width), so it may span two words.
+#[derive(Clone, PartialEq, Eq)]
+pub struct IntVector {
+	width: usize,
+	len: usize,
+	bits: BitVector,
+}
+
+impl IntVector {
+	// Panics if width > usize::BITS
+	pub fn new(width: usize) -> Self {
+		Self::with_capacity(width, 0)
+	}
+	pub fn with_capacity(width: usize, capacity: usize) -> Self {
+		assert!(width <= WORD_BITS, "Width is too large");
+		IntVector { width, len: 0, bits: BitVector::with_capacity(capacity * width) }
+	}
+	pub fn width(&self) -> usize {
+		self.width
+	}
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	pub fn bits(&self) -> &BitVector {
+		&self.bits
+	}
+	// Returns None, without pushing, if `value` does not fit in width() bits
+	pub fn push(&mut self, value: usize) -> Option<()> {
+		if !self.fits(value) { return None }
+
+		self.bits.push_word(value, self.width);
+		self.len += 1;
+		Some(())
+	}
+	pub fn pop(&mut self) -> Option<usize> {
+		let value = self.get(self.len.checked_sub(1)?)?;
+		self.len -= 1;
+		self.bits.truncate(self.len * self.width);
+		Some(value)
+	}
+	pub fn get(&self, index: usize) -> Option<usize> {
+		if index >= self.len { return None }
+
+		Some(self.bits.load_word(index * self.width) & usize::low_mask(self.width))
+	}
+	// Returns None if index >= len() or `value` does not fit in width() bits
+	pub fn set(&mut self, index: usize, value: usize) -> Option<()> {
+		if index >= self.len || !self.fits(value) { return None }
+
+		self.bits.store_word(index * self.width, value, self.width);
+		Some(())
+	}
+	pub fn iter(&self) -> Iter<'_> {
+		Iter { ints: self, start: 0, end: self.len }
+	}
+	// Repacks the elements with a new width.
+	// Returns None, leaving the vector unchanged, if some element does not fit.
+	pub fn set_width(&mut self, width: usize) -> Option<()> {
+		assert!(width <= WORD_BITS, "Width is too large");
+		if width < self.width && self.iter().any(|value| min_width(value) > width) {
+			return None
+		}
+
+		let mut repacked = Self::with_capacity(width, self.len);
+		for value in self.iter() { repacked.push(value); }
+		*self = repacked;
+		Some(())
+	}
+	// Repacks the elements with the smallest width that fits them all
+	pub fn shrink_width(&mut self) {
+		let width = self.iter().map(min_width).max().unwrap_or(0);
+		self.set_width(width);
+	}
+
+	fn fits(&self, value: usize) -> bool {
+		min_width(value) <= self.width
+	}
+}
+
+// Uses the smallest width that fits every value
+impl FromIterator<usize> for IntVector {
+	fn from_iter<I: IntoIterator<Item=usize>>(iter: I) -> Self {
+		let values: Vec<_> = iter.into_iter().collect();
+		let width = values.iter().cloned().map(min_width).max().unwrap_or(0);
+		let mut ints = Self::with_capacity(width, values.len());
+		for value in values { ints.push(value); }
+		ints
+	}
+}
+
+impl Debug for IntVector {
+	fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+		fmt.debug_list().entries(self.iter()).finish()
+	}
+}
+
+pub struct Iter<'a> {
+	ints: &'a IntVector,
+	start: usize,
+	end: usize,
+}
+
+impl Iterator for Iter<'_> {
+	type Item = usize;
+
+	fn next(&mut self) -> Option<usize> {
+		if self.start == self.end { return None }
+
+		self.start += 1;
+		self.ints.get(self.start - 1)
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.end - self.start;
+		(len, Some(len))
+	}
+	fn nth(&mut self, n: usize) -> Option<usize> {
+		self.start += n.min(self.end - self.start);
+		self.next()
+	}
+}
+impl DoubleEndedIterator for Iter<'_> {
+	fn next_back(&mut self) -> Option<usize> {
+		if self.start == self.end { return None }
+
+		self.end -= 1;
+		self.ints.get(self.end)
+	}
+}
+impl ExactSizeIterator for Iter<'_> {}
+impl FusedIterator for Iter<'_> {}
+
+impl<'a> IntoIterator for &'a IntVector {
+	type Item = usize;
+	type IntoIter = Iter<'a>;
+
+	fn into_iter(self) -> Iter<'a> {
+		self.iter()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pattern(len: usize, width: usize) -> Vec<usize> {
+		(0..len).map(|i| (i * 2_654_435_761) & usize::low_mask(width)).collect()
+	}
+
+	#[test]
+	fn test_push_get_set() {
+		for width in (0..=WORD_BITS).step_by(3).chain(vec![WORD_BITS]) {
+			let values = pattern(200, width);
+			let mut ints = IntVector::new(width);
+			for &value in &values { ints.push(value).unwrap() }
+			assert_eq!(ints.len(), 200);
+			assert_eq!(ints.bits().len(), 200 * width);
+			assert!(ints.iter().eq(values.iter().cloned()));
+			assert!(ints.iter().rev().eq(values.iter().rev().cloned()));
+			assert_eq!(ints.get(200), None);
+			if width < WORD_BITS {
+				assert_eq!(ints.push(1 << width), None);
+				assert_eq!(ints.set(0, 1 << width), None);
+			}
+			for i in 0..200 { ints.set(i, values[199 - i]).unwrap() }
+			assert!(ints.iter().eq(values.iter().rev().cloned()));
+			assert_eq!(ints.set(200, 0), None);
+			for &value in &values { assert_eq!(ints.pop(), Some(value)) }
+			assert_eq!(ints.pop(), None);
+		}
+	}
+
+	#[test]
+	fn test_from_iter() {
+		let ints: IntVector = (0..1000).collect();
+		assert_eq!(ints.width(), 10);
+		assert!(ints.iter().eq(0..1000));
+		let ints: IntVector = vec![0, 0, 0].into_iter().collect();
+		assert_eq!(ints.width(), 0);
+		assert!(ints.iter().eq(vec![0, 0, 0]));
+		assert_eq!(IntVector::from_iter(vec![]).width(), 0);
+		assert_eq!(IntVector::from_iter(vec![usize::MAX, 1]).width(), WORD_BITS);
+	}
+
+	#[test]
+	fn test_set_width() {
+		let values = pattern(500, 7);
+		let mut ints: IntVector = values.iter().cloned().collect();
+		assert_eq!(ints.width(), 7);
+		ints.set_width(20).unwrap();
+		assert_eq!(ints.width(), 20);
+		assert_eq!(ints.bits().len(), 500 * 20);
+		assert!(ints.iter().eq(values.iter().cloned()));
+		ints.push(1 << 19).unwrap();
+		assert_eq!(ints.set_width(7), None);
+		assert_eq!(ints.width(), 20);
+		ints.pop();
+		ints.shrink_width();
+		assert_eq!(ints.width(), 7);
+		assert!(ints.iter().eq(values));
+	}
+}
@@ -0,0 +1,280 @@
+use std::fmt::{self, Debug, Formatter};
+use std::ops::Range;
+use super::{BitSlice, BitVector, WORD_BITS};
+
+// A matrix over GF(2), stored row-major with each row padded to a whole number of words.
+// The padding bits are always 0, so row operations can work on whole words.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BitMatrix {
+	rows: usize,
+	cols: usize,
+	row_words: usize,
+	words: Vec<usize>,
+}
+
+fn parity(words: impl Iterator<Item=usize>) -> bool {
+	words.fold(0, |parity, word| parity ^ word.count_ones()) & 1 != 0
+}
+
+impl BitMatrix {
+	pub fn new(rows: usize, cols: usize) -> Self {
+		let row_words = cols.div_ceil(WORD_BITS);
+		BitMatrix { rows, cols, row_words, words: vec![0; rows * row_words] }
+	}
+	pub fn identity(size: usize) -> Self {
+		let mut matrix = Self::new(size, size);
+		for i in 0..size { matrix.set(i, i, true); }
+		matrix
+	}
+	// Returns None if the rows have different lengths
+	pub fn from_rows(rows: &[BitVector]) -> Option<Self> {
+		let cols = rows.first().map_or(0, |row| row.len());
+		let mut matrix = Self::new(rows.len(), cols);
+		for (row, bits) in rows.iter().enumerate() {
+			if bits.len() != cols { return None }
+
+			matrix.row_mut(row).store_bits(0, bits);
+		}
+		Some(matrix)
+	}
+	pub fn rows(&self) -> usize {
+		self.rows
+	}
+	pub fn cols(&self) -> usize {
+		self.cols
+	}
+	pub fn get(&self, row: usize, col: usize) -> Option<bool> {
+		if row >= self.rows { return None }
+
+		self.row(row).get(col)
+	}
+	pub fn set(&mut self, row: usize, col: usize, value: bool) -> Option<()> {
+		if row >= self.rows { return None }
+
+		self.row_mut(row).set(col, value)
+	}
+	// Panics if row >= rows()
+	pub fn row(&self, row: usize) -> &BitSlice {
+		let words = &self.words[self.row_range(row)];
+		unsafe { BitSlice::from_raw_parts(words.as_ptr(), 0, self.cols) }
+	}
+	pub fn row_mut(&mut self, row: usize) -> &mut BitSlice {
+		let range = self.row_range(row);
+		let words = &mut self.words[range];
+		unsafe { BitSlice::from_raw_parts_mut(words.as_mut_ptr(), 0, self.cols) }
+	}
+
+	pub fn transpose(&self) -> Self {
+		let mut transposed = Self::new(self.cols, self.rows);
+		for row in 0..self.rows {
+			for col in self.row(row).iter_ones() { transposed.set(col, row, true); }
+		}
+		transposed
+	}
+	// Returns None unless vector.len() == cols()
+	pub fn mul_vector(&self, vector: &BitSlice) -> Option<BitVector> {
+		if vector.len() != self.cols { return None }
+
+		let vector = vector.to_bit_vector();
+		Some((0..self.rows).map(|row| {
+			let row_words = self.row_words(row).iter();
+			parity(row_words.zip(&vector.words).map(|(row_word, word)| row_word & word))
+		}).collect())
+	}
+	// Returns None unless other.rows() == cols()
+	pub fn mul_matrix(&self, other: &Self) -> Option<Self> {
+		if other.rows != self.cols { return None }
+
+		// Each row of the product is the sum of the rows of `other`
+		// selected by the ones in the corresponding row of `self`
+		let mut product = Self::new(self.rows, other.cols);
+		for row in 0..self.rows {
+			let product_range = product.row_range(row);
+			for col in self.row(row).iter_ones() {
+				let product_row = &mut product.words[product_range.clone()];
+				for (word, other_word) in product_row.iter_mut().zip(other.row_words(col)) {
+					*word ^= other_word;
+				}
+			}
+		}
+		Some(product)
+	}
+
+	// Puts the matrix in reduced row echelon form and returns the pivot column of each
+	// non-zero row. The non-zero rows come first, in order of their pivot columns.
+	pub fn row_reduce(&mut self) -> Vec<usize> {
+		let mut pivots = vec![];
+		for col in 0..self.cols {
+			let pivot_row = pivots.len();
+			let found = (pivot_row..self.rows).find(|&row| self.row(row).get(col) == Some(true));
+			let Some(found) = found else { continue };
+
+			self.swap_rows(pivot_row, found);
+			for row in 0..self.rows {
+				if row != pivot_row && self.row(row).get(col) == Some(true) {
+					self.xor_row(pivot_row, row);
+				}
+			}
+			pivots.push(col);
+		}
+		pivots
+	}
+	pub fn rank(&self) -> usize {
+		self.clone().row_reduce().len()
+	}
+	// A basis of the vectors x with Ax = 0
+	pub fn nullspace(&self) -> Vec<BitVector> {
+		let mut reduced = self.clone();
+		let pivots = reduced.row_reduce();
+		let mut pivot_cols = pivots.iter().peekable();
+		let free_cols = (0..self.cols).filter(|&col| {
+			let is_pivot = pivot_cols.peek() == Some(&&col);
+			if is_pivot { pivot_cols.next(); }
+			!is_pivot
+		});
+		// Setting one free variable determines each pivot variable from its row
+		free_cols.map(|free_col| {
+			let mut vector = self.zero_vector();
+			vector.set(free_col, true);
+			for (row, &pivot_col) in pivots.iter().enumerate() {
+				vector.set(pivot_col, reduced.row(row).get(free_col).unwrap());
+			}
+			vector
+		}).collect()
+	}
+	// Some x with Ax = b, or None if there is none or b.len() != rows()
+	pub fn solve(&self, b: &BitSlice) -> Option<BitVector> {
+		if b.len() != self.rows { return None }
+
+		// Row reduce [A | b]
+		let mut augmented = Self::new(self.rows, self.cols + 1);
+		for row in 0..self.rows {
+			let augmented_row = augmented.row_mut(row);
+			augmented_row.store_bits(0, self.row(row));
+			augmented_row.set(self.cols, b.get(row).unwrap());
+		}
+		let pivots = augmented.row_reduce();
+		if pivots.last() == Some(&self.cols) { return None }
+
+		let mut solution = self.zero_vector();
+		for (row, &pivot_col) in pivots.iter().enumerate() {
+			solution.set(pivot_col, augmented.get(row, self.cols).unwrap());
+		}
+		Some(solution)
+	}
+
+	fn zero_vector(&self) -> BitVector {
+		BitVector::from_storage(vec![0; self.row_words], self.cols).unwrap()
+	}
+	fn row_range(&self, row: usize) -> Range<usize> {
+		assert!(row < self.rows, "Row out of bounds");
+		row * self.row_words..(row + 1) * self.row_words
+	}
+	fn row_words(&self, row: usize) -> &[usize] {
+		&self.words[self.row_range(row)]
+	}
+	fn swap_rows(&mut self, row1: usize, row2: usize) {
+		if row1 == row2 { return }
+
+		for i in 0..self.row_words {
+			self.words.swap(row1 * self.row_words + i, row2 * self.row_words + i);
+		}
+	}
+	// Adds row `src` to row `dest`
+	fn xor_row(&mut self, src: usize, dest: usize) {
+		for i in 0..self.row_words {
+			let word = self.words[src * self.row_words + i];
+			self.words[dest * self.row_words + i] ^= word;
+		}
+	}
+}
+
+impl Debug for BitMatrix {
+	fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+		fmt.debug_list().entries((0..self.rows).map(|row| self.row(row))).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::FromIterator;
+
+	fn matrix(rows: &[&str]) -> BitMatrix {
+		let rows: Vec<_> =
+			rows.iter().map(|row| BitVector::from_iter(row.chars().map(|c| c == '1'))).collect();
+		BitMatrix::from_rows(&rows).unwrap()
+	}
+	fn vector(bits: &str) -> BitVector {
+		BitVector::from_iter(bits.chars().map(|c| c == '1'))
+	}
+
+	// A deterministic pseudo-random matrix
+	fn pseudo_random(rows: usize, cols: usize, seed: usize) -> BitMatrix {
+		let mut matrix = BitMatrix::new(rows, cols);
+		for row in 0..rows {
+			for col in 0..cols {
+				matrix.set(row, col, (row * 31 + col * 17 + seed) * 2_654_435_761 % 97 < 40);
+			}
+		}
+		matrix
+	}
+
+	#[test]
+	fn test_products() {
+		let a = matrix(&["110", "011"]);
+		assert_eq!(a.mul_vector(&vector("111")), Some(vector("00")));
+		assert_eq!(a.mul_vector(&vector("100")), Some(vector("10")));
+		assert_eq!(a.mul_vector(&vector("10")), None);
+		assert_eq!(a.transpose(), matrix(&["10", "11", "01"]));
+		assert_eq!(a.mul_matrix(&a.transpose()), Some(matrix(&["01", "10"])));
+		assert_eq!(a.mul_matrix(&a), None);
+
+		// (AB)^T = B^T A^T and (AB)x = A(Bx), across word boundaries
+		let a = pseudo_random(70, 130, 1);
+		let b = pseudo_random(130, 65, 2);
+		let product = a.mul_matrix(&b).unwrap();
+		assert_eq!(product.transpose(), b.transpose().mul_matrix(&a.transpose()).unwrap());
+		let x = pseudo_random(1, 65, 3).row(0).to_bit_vector();
+		let bx = b.mul_vector(&x).unwrap();
+		assert_eq!(product.mul_vector(&x), a.mul_vector(&bx));
+		assert_eq!(BitMatrix::identity(130).mul_matrix(&b), Some(b));
+	}
+
+	#[test]
+	fn test_row_reduce() {
+		let mut a = matrix(&["0110", "1101", "1011"]);
+		assert_eq!(a.rank(), 2);
+		assert_eq!(a.row_reduce(), [0, 1]);
+		assert_eq!(a, matrix(&["1011", "0110", "0000"]));
+		assert_eq!(BitMatrix::identity(100).rank(), 100);
+		assert_eq!(BitMatrix::new(5, 7).rank(), 0);
+	}
+
+	#[test]
+	fn test_nullspace() {
+		for &(rows, cols, seed) in &[(3, 5, 0), (40, 70, 1), (70, 40, 2), (64, 64, 3)] {
+			let a = pseudo_random(rows, cols, seed);
+			let basis = a.nullspace();
+			assert_eq!(basis.len(), cols - a.rank());
+			for x in &basis { assert!(a.mul_vector(x).unwrap().iter_ones().next().is_none()) }
+			// The basis vectors are independent
+			assert_eq!(BitMatrix::from_rows(&basis).map_or(0, |basis| basis.rank()), basis.len());
+		}
+	}
+
+	#[test]
+	fn test_solve() {
+		for &(rows, cols, seed) in &[(3, 5, 0), (40, 70, 1), (70, 40, 2), (64, 64, 3)] {
+			let a = pseudo_random(rows, cols, seed);
+			let x = pseudo_random(1, cols, seed + 10).row(0).to_bit_vector();
+			let b = a.mul_vector(&x).unwrap();
+			let solution = a.solve(&b).unwrap();
+			assert_eq!(a.mul_vector(&solution), Some(b));
+		}
+		let a = matrix(&["11", "11"]);
+		assert_eq!(a.solve(&vector("10")), None);
+		assert_eq!(a.solve(&vector("11")), Some(vector("10")));
+		assert_eq!(a.solve(&vector("1")), None);
+	}
+}
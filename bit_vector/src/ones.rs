@@ -0,0 +1,168 @@
+use super::{BitSlice, Word};
+
+impl<W: Word> BitSlice<W> {
+	pub fn count_ones(&self) -> usize {
+		(0..self.len()).step_by(W::BITS)
+			.map(|start| self.load_word(start).count_ones() as usize)
+			.sum()
+	}
+	pub fn count_zeros(&self) -> usize {
+		self.len() - self.count_ones()
+	}
+	pub fn iter_ones(&self) -> IterOnes<'_, W> {
+		IterOnes(BitPositions::new(self, false))
+	}
+	pub fn iter_zeros(&self) -> IterZeros<'_, W> {
+		IterZeros(BitPositions::new(self, true))
+	}
+	pub fn first_one(&self) -> Option<usize> {
+		self.next_one(0)
+	}
+	pub fn last_one(&self) -> Option<usize> {
+		let len = self.len();
+		if len == 0 { return None }
+
+		let last_start = (len - 1) / W::BITS * W::BITS;
+		(0..=last_start).rev().step_by(W::BITS).find_map(|start| {
+			let word = self.load_word(start);
+			if word == W::ZERO { None }
+			else { Some(start + (W::BITS - 1 - word.leading_zeros() as usize)) }
+		})
+	}
+	// Index of the first one at or after `from`
+	pub fn next_one(&self, from: usize) -> Option<usize> {
+		(from..self.len()).step_by(W::BITS).find_map(|start| {
+			let word = self.load_word(start);
+			if word == W::ZERO { None }
+			else { Some(start + word.trailing_zeros() as usize) }
+		})
+	}
+}
+
+// Scans a word at a time, yielding the indices of the bits in each word.
+// `invert` makes this find zeros instead of ones.
+struct BitPositions<'a, W: Word> {
+	bits: &'a BitSlice<W>,
+	invert: bool,
+	word_start: usize,
+	word: W,
+	next_word_start: usize,
+}
+
+impl<'a, W: Word> BitPositions<'a, W> {
+	fn new(bits: &'a BitSlice<W>, invert: bool) -> Self {
+		BitPositions { bits, invert, word_start: 0, word: W::ZERO, next_word_start: 0 }
+	}
+}
+
+impl<W: Word> Iterator for BitPositions<'_, W> {
+	type Item = usize;
+
+	fn next(&mut self) -> Option<usize> {
+		loop {
+			if self.word != W::ZERO {
+				let offset = self.word.trailing_zeros() as usize;
+				self.word &= self.word - W::ONE;
+				return Some(self.word_start + offset)
+			}
+
+			let len = self.bits.len();
+			if self.next_word_start >= len { return None }
+
+			self.word_start = self.next_word_start;
+			self.word = self.bits.load_word(self.word_start);
+			if self.invert {
+				self.word = !self.word & W::low_mask(len - self.word_start);
+			}
+			self.next_word_start += W::BITS;
+		}
+	}
+}
+
+pub struct IterOnes<'a, W: Word = usize>(BitPositions<'a, W>);
+
+impl<W: Word> Iterator for IterOnes<'_, W> {
+	type Item = usize;
+
+	fn next(&mut self) -> Option<usize> {
+		self.0.next()
+	}
+}
+
+pub struct IterZeros<'a, W: Word = usize>(BitPositions<'a, W>);
+
+impl<W: Word> Iterator for IterZeros<'_, W> {
+	type Item = usize;
+
+	fn next(&mut self) -> Option<usize> {
+		self.0.next()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::FromIterator;
+	use crate::BitVector;
+
+	fn sparse_pattern(len: usize, spacing: usize) -> BitVector {
+		BitVector::from_iter((0..len).map(|i| (i * 13 + i / 7) % spacing == 0))
+	}
+
+	fn check_against_scan(bits: &BitSlice) {
+		let values: Vec<_> = bits.iter().collect();
+		let ones: Vec<_> = (0..values.len()).filter(|&i| values[i]).collect();
+		let zeros: Vec<_> = (0..values.len()).filter(|&i| !values[i]).collect();
+		assert_eq!(bits.count_ones(), ones.len());
+		assert_eq!(bits.count_zeros(), zeros.len());
+		assert_eq!(bits.iter_ones().collect::<Vec<_>>(), ones);
+		assert_eq!(bits.iter_zeros().collect::<Vec<_>>(), zeros);
+		assert_eq!(bits.first_one(), ones.first().cloned());
+		assert_eq!(bits.last_one(), ones.last().cloned());
+		for from in 0..=values.len() {
+			let next = ones.iter().cloned().find(|&one| one >= from);
+			assert_eq!(bits.next_one(from), next);
+		}
+	}
+
+	#[test]
+	fn test_vectors() {
+		for len in 0..200 {
+			for &spacing in &[1, 2, 5, 70, 1000] {
+				check_against_scan(&sparse_pattern(len, spacing))
+			}
+		}
+	}
+
+	#[test]
+	fn test_slices() {
+		let bits = sparse_pattern(500, 11);
+		for start in 0..70 {
+			for &len in &[0, 1, 63, 64, 65, 300] {
+				check_against_scan(&bits[start..(start + len)])
+			}
+		}
+	}
+
+	#[test]
+	fn test_unused_bits() {
+		let mut bits = BitVector::from_iter(vec![true; 100]);
+		for _ in 0..40 { bits.pop(); }
+		bits.push(false);
+		assert_eq!(bits.count_ones(), 60);
+		assert_eq!(bits.count_zeros(), 1);
+		assert_eq!(bits.last_one(), Some(59));
+		assert_eq!(bits.iter_zeros().collect::<Vec<_>>(), [60]);
+		assert_eq!(bits.next_one(60), None);
+		check_against_scan(&bits);
+	}
+
+	#[test]
+	fn test_sparse() {
+		let mut bits = BitVector::from_iter(vec![false; 1_000_000]);
+		for &i in &[3, 64, 65, 500_000, 999_999] { bits.set(i, true); }
+		assert_eq!(bits.iter_ones().collect::<Vec<_>>(), [3, 64, 65, 500_000, 999_999]);
+		assert_eq!(bits.next_one(66), Some(500_000));
+		assert_eq!(bits.count_ones(), 5);
+	}
+}
@@ -0,0 +1,327 @@
+use std::ops::{
+	BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not,
+	Shl, ShlAssign, Shr, ShrAssign,
+};
+use super::BitVector;
+
+impl BitVector {
+	// Applies `op` to each pair of words, zero-extending the shorter vector.
+	// The unused bits of the result are left as zeros.
+	fn combine_words<F: Fn(usize, usize) -> usize>(&mut self, other: &BitVector, op: F) {
+		self.clear_unused_bits();
+		if other.len > self.len {
+			self.words.resize(Self::to_words_ceil(other.len), 0);
+			self.len = other.len;
+		}
+		let other_words = Self::to_words_ceil(other.len);
+		let other_extra_bits = Self::to_word_offset(other.len);
+		for (index, word) in self.words.iter_mut().enumerate() {
+			let other_word =
+				if index >= other_words { 0 }
+				else if index + 1 == other_words && other_extra_bits > 0 {
+					other.words[index] & ((1 << other_extra_bits) - 1)
+				}
+				else { other.words[index] };
+			*word = op(*word, other_word);
+		}
+	}
+}
+
+// Binary operators on vectors of different lengths treat the shorter vector
+// as if it were padded with zeros, so the result has the longer length.
+// This is also true for the assigning operators, which may lengthen `self`.
+macro_rules! bitwise_op {
+	($op:ident, $op_fn:ident, $assign:ident, $assign_fn:ident, $word_op:expr) => {
+		impl $assign<&BitVector> for BitVector {
+			fn $assign_fn(&mut self, other: &BitVector) {
+				self.combine_words(other, $word_op)
+			}
+		}
+		impl $assign<BitVector> for BitVector {
+			fn $assign_fn(&mut self, other: BitVector) {
+				self.$assign_fn(&other)
+			}
+		}
+
+		impl $op<&BitVector> for BitVector {
+			type Output = BitVector;
+
+			fn $op_fn(mut self, other: &BitVector) -> BitVector {
+				self.$assign_fn(other);
+				self
+			}
+		}
+		impl $op<BitVector> for BitVector {
+			type Output = BitVector;
+
+			fn $op_fn(self, other: BitVector) -> BitVector {
+				self.$op_fn(&other)
+			}
+		}
+		impl $op<&BitVector> for &BitVector {
+			type Output = BitVector;
+
+			fn $op_fn(self, other: &BitVector) -> BitVector {
+				self.clone().$op_fn(other)
+			}
+		}
+		impl $op<BitVector> for &BitVector {
+			type Output = BitVector;
+
+			// The operation is commutative, so reuse other's allocation
+			fn $op_fn(self, other: BitVector) -> BitVector {
+				other.$op_fn(self)
+			}
+		}
+	};
+}
+
+bitwise_op!(BitAnd, bitand, BitAndAssign, bitand_assign, |a, b| a & b);
+bitwise_op!(BitOr, bitor, BitOrAssign, bitor_assign, |a, b| a | b);
+bitwise_op!(BitXor, bitxor, BitXorAssign, bitxor_assign, |a, b| a ^ b);
+
+impl Not for BitVector {
+	type Output = BitVector;
+
+	fn not(mut self) -> BitVector {
+		let word_count = Self::to_words_ceil(self.len);
+		for word in &mut self.words[..word_count] { *word = !*word }
+		self
+	}
+}
+impl Not for &BitVector {
+	type Output = BitVector;
+
+	fn not(self) -> BitVector {
+		!self.clone()
+	}
+}
+
+// Shifts and rotations treat index 0 as the least significant bit, like the
+// words themselves, so shifting left moves bits to higher indices.
+// The length is unchanged: bits shifted past either end are discarded.
+impl ShlAssign<usize> for BitVector {
+	fn shl_assign(&mut self, shift: usize) {
+		let (shift, kept) = self.split_shift(shift);
+		self.move_bits(0, shift, kept);
+		self.fill_bits(0, shift, false);
+	}
+}
+impl ShrAssign<usize> for BitVector {
+	fn shr_assign(&mut self, shift: usize) {
+		let (shift, kept) = self.split_shift(shift);
+		self.move_bits(shift, 0, kept);
+		self.fill_bits(kept, shift, false);
+	}
+}
+impl Shl<usize> for BitVector {
+	type Output = BitVector;
+
+	fn shl(mut self, shift: usize) -> BitVector {
+		self <<= shift;
+		self
+	}
+}
+impl Shr<usize> for BitVector {
+	type Output = BitVector;
+
+	fn shr(mut self, shift: usize) -> BitVector {
+		self >>= shift;
+		self
+	}
+}
+impl Shl<usize> for &BitVector {
+	type Output = BitVector;
+
+	fn shl(self, shift: usize) -> BitVector {
+		self.clone() << shift
+	}
+}
+impl Shr<usize> for &BitVector {
+	type Output = BitVector;
+
+	fn shr(self, shift: usize) -> BitVector {
+		self.clone() >> shift
+	}
+}
+
+impl BitVector {
+	// Returns the number of bits shifted out and the number of bits kept
+	fn split_shift(&self, shift: usize) -> (usize, usize) {
+		let shift = shift.min(self.len);
+		(shift, self.len - shift)
+	}
+
+	// Moves bit i to index (i + rotation) % len
+	pub fn rotate_left(&mut self, rotation: usize) {
+		let len = self.len;
+		if len == 0 { return }
+
+		// Save whichever side is smaller while the other side is moved
+		let rotation = rotation % len;
+		if rotation <= len - rotation {
+			let wrapped = self[(len - rotation)..].to_bit_vector();
+			self.move_bits(0, rotation, len - rotation);
+			self.store_bits(0, &wrapped);
+		}
+		else {
+			let moved = self[..(len - rotation)].to_bit_vector();
+			self.move_bits(len - rotation, 0, rotation);
+			self.store_bits(rotation, &moved);
+		}
+	}
+	// Moves bit i to index (i - rotation) % len
+	pub fn rotate_right(&mut self, rotation: usize) {
+		if self.len == 0 { return }
+
+		let len = self.len;
+		self.rotate_left(len - rotation % len)
+	}
+	pub fn rotated_left(&self, rotation: usize) -> Self {
+		let mut rotated = self.clone();
+		rotated.rotate_left(rotation);
+		rotated
+	}
+	pub fn rotated_right(&self, rotation: usize) -> Self {
+		let mut rotated = self.clone();
+		rotated.rotate_right(rotation);
+		rotated
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::FromIterator;
+
+	fn pattern(len: usize, seed: usize) -> BitVector {
+		BitVector::from_iter((0..len).map(|i| (i * i + seed) % 7 < 3))
+	}
+
+	fn bitwise_brute<F: Fn(bool, bool) -> bool>(a: &BitVector, b: &BitVector, op: F)
+		-> BitVector
+	{
+		let len = a.len().max(b.len());
+		BitVector::from_iter((0..len).map(|i|
+			op(a.get(i).unwrap_or(false), b.get(i).unwrap_or(false))
+		))
+	}
+
+	#[test]
+	fn test_binary_ops() {
+		let lengths = [0, 1, 5, 63, 64, 65, 127, 128, 129, 300];
+		for &a_len in &lengths {
+			for &b_len in &lengths {
+				let a = pattern(a_len, 1);
+				let b = pattern(b_len, 4);
+				let and = bitwise_brute(&a, &b, |a, b| a & b);
+				let or = bitwise_brute(&a, &b, |a, b| a | b);
+				let xor = bitwise_brute(&a, &b, |a, b| a ^ b);
+				assert_eq!(&a & &b, and);
+				assert_eq!(&a | &b, or);
+				assert_eq!(&a ^ &b, xor);
+				assert_eq!(a.clone() & &b, and);
+				assert_eq!(&a | b.clone(), or);
+				assert_eq!(a.clone() ^ b.clone(), xor);
+				let mut assigned = a.clone();
+				assigned &= &b;
+				assert_eq!(assigned, and);
+				let mut assigned = a.clone();
+				assigned |= b.clone();
+				assert_eq!(assigned, or);
+				let mut assigned = a.clone();
+				assigned ^= &b;
+				assert_eq!(assigned, xor);
+				assert!(assigned.bytes().eq(xor.bytes()));
+			}
+		}
+	}
+
+	#[test]
+	fn test_not() {
+		for len in 0..200 {
+			let bits = pattern(len, 2);
+			let negated = !&bits;
+			assert_eq!(negated, BitVector::from_iter((&bits).into_iter().map(|b| !b)));
+			assert_eq!(!negated, bits);
+		}
+	}
+
+	#[test]
+	fn test_unused_bits() {
+		// Bits past the end of a vector must not leak into the result
+		let mut ones = BitVector::from_iter(vec![true; 100]);
+		for _ in 0..60 { ones.pop(); }
+		let zeros = BitVector::from_iter(vec![false; 70]);
+		let mut expected = BitVector::from_iter(vec![true; 40]);
+		expected.extend(vec![false; 30]);
+		assert_eq!(&ones | &zeros, expected);
+		assert_eq!(&zeros ^ &ones, expected);
+		assert!((&ones | &zeros).bytes().eq(expected.bytes()));
+		let mut grown = ones.clone();
+		grown |= &zeros;
+		grown.push(true);
+		expected.push(true);
+		assert_eq!(grown, expected);
+		assert_eq!(!(!&ones), ones);
+	}
+
+	#[test]
+	fn test_shifts() {
+		for &len in &[0, 1, 5, 63, 64, 65, 200] {
+			let bits = pattern(len, 3);
+			for shift in 0..(len + 70) {
+				let shifted_left = BitVector::from_iter((0..len).map(|i|
+					i >= shift && bits.get(i - shift).unwrap()
+				));
+				let shifted_right = BitVector::from_iter((0..len).map(|i|
+					bits.get(i + shift).unwrap_or(false)
+				));
+				assert_eq!(&bits << shift, shifted_left);
+				assert_eq!(&bits >> shift, shifted_right);
+				let mut assigned = bits.clone();
+				assigned <<= shift;
+				assert_eq!(assigned, shifted_left);
+				let mut assigned = bits.clone();
+				assigned >>= shift;
+				assert_eq!(assigned, shifted_right);
+			}
+		}
+	}
+
+	#[test]
+	fn test_rotates() {
+		for &len in &[0, 1, 5, 63, 64, 65, 200] {
+			let bits = pattern(len, 5);
+			for rotation in 0..(2 * len + 3) {
+				let rotated_left = BitVector::from_iter((0..len).map(|i|
+					bits.get((i + len - rotation % len) % len).unwrap()
+				));
+				let rotated_right = BitVector::from_iter((0..len).map(|i|
+					bits.get((i + rotation) % len).unwrap()
+				));
+				assert_eq!(bits.rotated_left(rotation), rotated_left);
+				assert_eq!(bits.rotated_right(rotation), rotated_right);
+				let mut rotated = bits.clone();
+				rotated.rotate_left(rotation);
+				rotated.rotate_right(rotation);
+				assert_eq!(rotated, bits);
+			}
+		}
+	}
+
+	#[test]
+	fn test_shift_and() {
+		// Bitap search for "aba" in "ababa"
+		let pattern_mask = |c| BitVector::from_iter("aba".chars().map(|p| p == c));
+		let mut state = BitVector::from_iter(vec![false; 3]);
+		let mut matches = vec![];
+		for (i, c) in "ababa".chars().enumerate() {
+			state <<= 1;
+			state.set(0, true);
+			state &= pattern_mask(c);
+			if state.get(2) == Some(true) { matches.push(i) }
+		}
+		assert_eq!(matches, [2, 4]);
+	}
+}
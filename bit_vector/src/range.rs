@@ -0,0 +1,149 @@
+use std::ops::RangeBounds;
+use super::{range_bounds, BitSlice, Word};
+
+// Operations on a range of bits, one word at a time.
+// Each returns None if the range does not lie within the slice.
+impl<W: Word> BitSlice<W> {
+	pub fn set_range<R: RangeBounds<usize>>(&mut self, range: R, value: bool) -> Option<()> {
+		let (start, end) = range_bounds(range, self.len())?;
+		self.update_words(start, end, |_, _| W::fill(value));
+		Some(())
+	}
+	pub fn flip_range<R: RangeBounds<usize>>(&mut self, range: R) -> Option<()> {
+		let (start, end) = range_bounds(range, self.len())?;
+		self.update_words(start, end, |word, _| !word);
+		Some(())
+	}
+	pub fn count_ones_in<R: RangeBounds<usize>>(&self, range: R) -> Option<usize> {
+		let (start, end) = range_bounds(range, self.len())?;
+		let mut count = 0;
+		self.for_each_word(start, end, |word, mask| count += (word & mask).count_ones() as usize);
+		Some(count)
+	}
+	pub fn count_zeros_in<R: RangeBounds<usize>>(&self, range: R) -> Option<usize> {
+		let (start, end) = range_bounds(range, self.len())?;
+		Some(end - start - self.count_ones_in(start..end)?)
+	}
+	// Whether any bit in the range is set
+	pub fn any_in<R: RangeBounds<usize>>(&self, range: R) -> Option<bool> {
+		let (start, end) = range_bounds(range, self.len())?;
+		let mut any = false;
+		self.for_each_word(start, end, |word, mask| any |= word & mask != W::ZERO);
+		Some(any)
+	}
+	// Whether every bit in the range is set (true for an empty range)
+	pub fn all_in<R: RangeBounds<usize>>(&self, range: R) -> Option<bool> {
+		let (start, end) = range_bounds(range, self.len())?;
+		let mut all = true;
+		self.for_each_word(start, end, |word, mask| all &= word & mask == mask);
+		Some(all)
+	}
+	// Copies the bits in `src` to start at `dest`; the ranges may overlap
+	pub fn copy_within<R: RangeBounds<usize>>(&mut self, src: R, dest: usize) -> Option<()> {
+		let (start, end) = range_bounds(src, self.len())?;
+		let count = end - start;
+		if dest > self.len() - count { return None }
+
+		self.move_bits(start, dest, count);
+		Some(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::FromIterator;
+	use crate::BitVector;
+
+	fn pattern(len: usize) -> Vec<bool> {
+		(0..len).map(|i| (i * 2_654_435_761) >> 7 & 3 == 0).collect()
+	}
+
+	// Ranges starting and ending in every position relative to word boundaries
+	fn ranges(len: usize) -> impl Iterator<Item=(usize, usize)> {
+		let points: Vec<_> = (0..len.min(70)).chain((70..=len).step_by(29)).chain(Some(len)).collect();
+		let points2 = points.clone();
+		points.into_iter().flat_map(move |start| {
+			points2.clone().into_iter().filter(move |&end| end >= start).map(move |end| (start, end))
+		})
+	}
+
+	fn check_queries(bits: &BitSlice, values: &[bool]) {
+		for (start, end) in ranges(values.len()) {
+			let range = &values[start..end];
+			let ones = range.iter().filter(|&&bit| bit).count();
+			assert_eq!(bits.count_ones_in(start..end), Some(ones));
+			assert_eq!(bits.count_zeros_in(start..end), Some(range.len() - ones));
+			assert_eq!(bits.any_in(start..end), Some(ones > 0));
+			assert_eq!(bits.all_in(start..end), Some(ones == range.len()));
+		}
+		let len = values.len();
+		assert_eq!(bits.count_ones_in(..len + 1), None);
+		assert_eq!(bits.any_in(len + 1..), None);
+		assert_eq!(bits.all_in(..), Some(values.iter().all(|&bit| bit)));
+	}
+
+	#[test]
+	fn test_queries() {
+		for &len in &[0, 1, 63, 64, 65, 200] {
+			let values = pattern(len);
+			let bits = BitVector::from_iter(values.iter());
+			check_queries(&bits, &values);
+			// A slice that starts partway through a word
+			if len > 10 { check_queries(&bits[3..len - 5], &values[3..len - 5]) }
+		}
+		let ones = BitVector::from_iter(vec![true; 130]);
+		assert_eq!(ones.all_in(1..129), Some(true));
+		assert_eq!(ones.count_ones_in(..), Some(130));
+	}
+
+	#[test]
+	fn test_set_and_flip() {
+		let len = 200;
+		for (start, end) in ranges(len) {
+			let mut values = pattern(len);
+			let mut bits = BitVector::from_iter(values.iter());
+			bits.set_range(start..end, true).unwrap();
+			for value in &mut values[start..end] { *value = true }
+			assert!(bits.iter().eq(values.iter().cloned()));
+			bits.flip_range(start / 2..end).unwrap();
+			for value in &mut values[start / 2..end] { *value = !*value }
+			assert!(bits.iter().eq(values.iter().cloned()));
+			if end > start {
+				bits.set_range(start..=end - 1, false).unwrap();
+				for value in &mut values[start..end] { *value = false }
+				assert!(bits.iter().eq(values.iter().cloned()));
+			}
+		}
+		let mut bits = BitVector::from_iter(pattern(10));
+		assert_eq!(bits.set_range(5..11, true), None);
+		assert_eq!(bits.flip_range(11..), None);
+		assert!(bits.iter().eq(pattern(10)));
+
+		// Only bits inside a sub-slice change
+		let mut bits = BitVector::from_iter(vec![false; 150]);
+		bits[10..140].set_range(60.., true).unwrap();
+		assert_eq!(bits.count_ones_in(..), Some(70));
+		assert_eq!(bits.all_in(70..140), Some(true));
+	}
+
+	#[test]
+	fn test_copy_within() {
+		let len = 200;
+		for (start, end) in ranges(len).step_by(7) {
+			for &dest in &[0, 1, start / 3, start, start + 1, 65, len - (end - start)] {
+				if dest + end - start > len { continue }
+
+				let mut values = pattern(len);
+				let mut bits = BitVector::from_iter(values.iter());
+				bits.copy_within(start..end, dest).unwrap();
+				values.copy_within(start..end, dest);
+				assert!(bits.iter().eq(values.iter().cloned()));
+			}
+		}
+		let mut bits = BitVector::from_iter(pattern(10));
+		assert_eq!(bits.copy_within(0..5, 6), None);
+		assert_eq!(bits.copy_within(0..11, 0), None);
+		assert_eq!(bits.copy_within(10.., 10), Some(()));
+	}
+}
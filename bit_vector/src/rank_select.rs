@@ -0,0 +1,218 @@
+use super::{BitVector, WORD_BITS};
+
+// Blocks store 16-bit counts relative to their superblock and superblocks
+// store absolute counts, which costs about 3% on top of the bits themselves.
+// Ranks within a block are finished with at most 8 popcounts.
+const BLOCK_BITS: usize = 512;
+const BLOCK_WORDS: usize = BLOCK_BITS / WORD_BITS;
+const SUPERBLOCK_BITS: usize = 1 << 16;
+const SUPERBLOCK_BLOCKS: usize = SUPERBLOCK_BITS / BLOCK_BITS;
+
+pub struct RankSelect {
+	bits: BitVector,
+	superblock_ranks: Vec<usize>,
+	block_ranks: Vec<u16>,
+	ones: usize,
+}
+
+// Finds the last index in start..end whose count is <= target,
+// assuming count(start) <= target and count is nondecreasing
+fn last_at_most<F: Fn(usize) -> usize>(mut start: usize, mut end: usize, target: usize, count: F)
+	-> usize
+{
+	while end - start > 1 {
+		let mid = start + (end - start) / 2;
+		if count(mid) <= target { start = mid } else { end = mid }
+	}
+	start
+}
+
+fn select_in_word(mut word: usize, rank: usize) -> usize {
+	for _ in 0..rank { word &= word - 1 }
+	word.trailing_zeros() as usize
+}
+
+impl RankSelect {
+	pub fn new(mut bits: BitVector) -> Self {
+		bits.clear_unused_bits();
+		let block_count = bits.words.len().div_ceil(BLOCK_WORDS);
+		let mut superblock_ranks =
+			Vec::with_capacity(block_count.div_ceil(SUPERBLOCK_BLOCKS));
+		let mut block_ranks = Vec::with_capacity(block_count);
+		let mut ones = 0;
+		for (block, block_words) in bits.words.chunks(BLOCK_WORDS).enumerate() {
+			if block % SUPERBLOCK_BLOCKS == 0 { superblock_ranks.push(ones) }
+			block_ranks.push((ones - superblock_ranks.last().unwrap()) as u16);
+			ones += block_words.iter().map(|word| word.count_ones() as usize).sum::<usize>();
+		}
+		RankSelect { bits, superblock_ranks, block_ranks, ones }
+	}
+
+	pub fn bits(&self) -> &BitVector {
+		&self.bits
+	}
+	pub fn into_bits(self) -> BitVector {
+		self.bits
+	}
+	pub fn len(&self) -> usize {
+		self.bits.len
+	}
+	pub fn is_empty(&self) -> bool {
+		self.bits.is_empty()
+	}
+	// The bits plus the rank index
+	pub fn size_in_bits(&self) -> usize {
+		self.bits.len +
+			self.superblock_ranks.len() * WORD_BITS +
+			self.block_ranks.len() * u16::BITS as usize
+	}
+	pub fn count_ones(&self) -> usize {
+		self.ones
+	}
+	pub fn count_zeros(&self) -> usize {
+		self.bits.len - self.ones
+	}
+
+	// Number of ones in bits[..index]
+	pub fn rank1(&self, index: usize) -> Option<usize> {
+		if index > self.bits.len { return None }
+
+		let word_index = BitVector::to_word_index(index);
+		let block = word_index / BLOCK_WORDS;
+		if block == self.block_ranks.len() { return Some(self.ones) }
+
+		let words = &self.bits.words;
+		let mut rank = self.block_rank(block);
+		for word in &words[(block * BLOCK_WORDS)..word_index] {
+			rank += word.count_ones() as usize
+		}
+		let word_offset = BitVector::to_word_offset(index) as usize;
+		if word_offset > 0 {
+			rank += (words[word_index] << (WORD_BITS - word_offset)).count_ones() as usize
+		}
+		Some(rank)
+	}
+	// Number of zeros in bits[..index]
+	pub fn rank0(&self, index: usize) -> Option<usize> {
+		self.rank1(index).map(|ones| index - ones)
+	}
+	// Index of the one with the given rank, i.e. preceded by `rank` ones
+	pub fn select1(&self, rank: usize) -> Option<usize> {
+		if rank >= self.ones { return None }
+
+		Some(self.select(rank, |ones, _| ones, |word| word))
+	}
+	// Index of the zero with the given rank, i.e. preceded by `rank` zeros
+	pub fn select0(&self, rank: usize) -> Option<usize> {
+		if rank >= self.count_zeros() { return None }
+
+		Some(self.select(rank, |ones, bits| bits - ones, |word| !word))
+	}
+
+	fn block_rank(&self, block: usize) -> usize {
+		self.superblock_ranks[block / SUPERBLOCK_BLOCKS] + self.block_ranks[block] as usize
+	}
+	// `count` converts (ones, bits) before a position into the number of
+	// selected bits before it, and `select_word` makes the selected bits ones
+	fn select<C, W>(&self, rank: usize, count: C, select_word: W) -> usize
+		where C: Fn(usize, usize) -> usize, W: Fn(usize) -> usize
+	{
+		let superblock = last_at_most(0, self.superblock_ranks.len(), rank, |superblock|
+			count(self.superblock_ranks[superblock], superblock * SUPERBLOCK_BITS)
+		);
+		let first_block = superblock * SUPERBLOCK_BLOCKS;
+		let end_block = self.block_ranks.len().min(first_block + SUPERBLOCK_BLOCKS);
+		let block = last_at_most(first_block, end_block, rank, |block|
+			count(self.block_rank(block), block * BLOCK_BITS)
+		);
+		let mut remaining = rank - count(self.block_rank(block), block * BLOCK_BITS);
+		let mut word_index = block * BLOCK_WORDS;
+		loop {
+			let word = select_word(self.bits.words[word_index]);
+			let word_count = word.count_ones() as usize;
+			if remaining < word_count {
+				return BitVector::from_word_index(word_index) + select_in_word(word, remaining)
+			}
+
+			remaining -= word_count;
+			word_index += 1;
+		}
+	}
+}
+
+impl From<BitVector> for RankSelect {
+	fn from(bits: BitVector) -> Self {
+		Self::new(bits)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::iter::FromIterator;
+
+	fn pseudo_random_bits(len: usize, one_chance: u64) -> BitVector {
+		let mut state = 0x2545_F491_4F6C_DD1Du64;
+		BitVector::from_iter((0..len).map(|_| {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			state % 100 < one_chance
+		}))
+	}
+
+	fn check_against_scan(bits: BitVector) {
+		let values: Vec<_> = (&bits).into_iter().collect();
+		let rank_select = RankSelect::new(bits);
+		let (mut ones, mut zeros) = (0, 0);
+		for (index, &value) in values.iter().enumerate() {
+			assert_eq!(rank_select.rank1(index), Some(ones));
+			assert_eq!(rank_select.rank0(index), Some(zeros));
+			if value {
+				assert_eq!(rank_select.select1(ones), Some(index));
+				ones += 1;
+			}
+			else {
+				assert_eq!(rank_select.select0(zeros), Some(index));
+				zeros += 1;
+			}
+		}
+		assert_eq!(rank_select.rank1(values.len()), Some(ones));
+		assert_eq!(rank_select.rank0(values.len()), Some(zeros));
+		assert_eq!(rank_select.rank1(values.len() + 1), None);
+		assert_eq!(rank_select.select1(ones), None);
+		assert_eq!(rank_select.select0(zeros), None);
+		assert_eq!(rank_select.count_ones(), ones);
+		assert_eq!(rank_select.count_zeros(), zeros);
+	}
+
+	#[test]
+	fn test_empty() {
+		check_against_scan(BitVector::new());
+	}
+
+	#[test]
+	fn test_lengths() {
+		for len in 0..1100 { check_against_scan(pseudo_random_bits(len, 50)) }
+	}
+
+	#[test]
+	fn test_densities() {
+		for &one_chance in &[0, 1, 10, 50, 90, 99, 100] {
+			check_against_scan(pseudo_random_bits(200_000, one_chance))
+		}
+	}
+
+	#[test]
+	fn test_unused_bits() {
+		// Popped bits must not be counted
+		let mut bits = BitVector::from_iter(vec![true; 100]);
+		for _ in 0..37 { bits.pop(); }
+		let rank_select = RankSelect::new(bits);
+		assert_eq!(rank_select.rank1(63), Some(63));
+		assert_eq!(rank_select.count_ones(), 63);
+		assert_eq!(rank_select.select1(62), Some(62));
+		assert_eq!(rank_select.select1(63), None);
+		assert_eq!(rank_select.select0(0), None);
+	}
+}
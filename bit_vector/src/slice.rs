@@ -0,0 +1,429 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+use std::ops::{
+	Deref, DerefMut, Index, IndexMut,
+	Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive, RangeBounds,
+};
+use std::ptr;
+use super::{range_bounds, GenericBitVector, Iter, Storage, StorageMut, Word};
+
+// A view of a range of bits, which need not start on a word boundary.
+// &BitSlice and &mut BitSlice are fat pointers: the data pointer is the word
+// containing the first bit, and the length stores the number of bits
+// shifted left by W::LOG_BITS, or'd with the offset of the first bit.
+// The [()] never refers to any memory itself, so this metadata is arbitrary.
+// Since a reference to [()] covers no bytes, the words cannot be accessed through it.
+// Instead, the pointer to the words is exposed when the slice is created,
+// and words() recovers that pointer's provenance from the address.
+pub struct BitSlice<W: Word = usize> {
+	_word: PhantomData<W>,
+	_bits: [()],
+}
+
+impl<W: Word> BitSlice<W> {
+	// `start` may be any bit index; it is normalized to a word and offset
+	pub(crate) unsafe fn from_raw_parts<'a>(
+		words: *const W,
+		start: usize,
+		len: usize,
+	) -> &'a Self {
+		let words = words.add(start >> W::LOG_BITS);
+		words.expose_provenance();
+		let metadata = Self::metadata(start, len);
+		&*(ptr::slice_from_raw_parts(words as *const (), metadata) as *const Self)
+	}
+	pub(crate) unsafe fn from_raw_parts_mut<'a>(
+		words: *mut W,
+		start: usize,
+		len: usize,
+	) -> &'a mut Self {
+		let words = words.add(start >> W::LOG_BITS);
+		words.expose_provenance();
+		let metadata = Self::metadata(start, len);
+		&mut *(ptr::slice_from_raw_parts_mut(words as *mut (), metadata) as *mut Self)
+	}
+	fn metadata(start: usize, len: usize) -> usize {
+		assert!(len <= usize::MAX >> W::LOG_BITS, "BitSlice is too long");
+		len << W::LOG_BITS | start & (W::BITS - 1)
+	}
+
+	pub fn len(&self) -> usize {
+		self._bits.len() >> W::LOG_BITS
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+	pub fn get(&self, index: usize) -> Option<bool> {
+		if index >= self.len() { return None }
+
+		Some(unsafe { self.get_unchecked(index) })
+	}
+	/// # Safety
+	/// `index` must be less than `self.len()`
+	pub unsafe fn get_unchecked(&self, index: usize) -> bool {
+		let bit = self.offset() + index;
+		let word = *self.words().add(bit >> W::LOG_BITS);
+		word >> (bit & (W::BITS - 1)) & W::ONE != W::ZERO
+	}
+	pub fn set(&mut self, index: usize, value: bool) -> Option<()> {
+		if index >= self.len() { return None }
+
+		unsafe { self.set_unchecked(index, value) }
+		Some(())
+	}
+	/// # Safety
+	/// `index` must be less than `self.len()`
+	pub unsafe fn set_unchecked(&mut self, index: usize, value: bool) {
+		let bit = self.offset() + index;
+		let word = &mut *self.words_mut().add(bit >> W::LOG_BITS);
+		let set_bit = W::ONE << (bit & (W::BITS - 1));
+		*word = *word & !set_bit | W::fill(value) & set_bit;
+	}
+	pub fn iter(&self) -> Iter<'_, W> {
+		self.into_iter()
+	}
+
+	pub fn get_range<R: RangeBounds<usize>>(&self, range: R) -> Option<&Self> {
+		let (start, end) = range_bounds(range, self.len())?;
+		Some(unsafe { Self::from_raw_parts(self.words(), self.offset() + start, end - start) })
+	}
+	pub fn get_range_mut<R: RangeBounds<usize>>(&mut self, range: R) -> Option<&mut Self> {
+		let (start, end) = range_bounds(range, self.len())?;
+		let start = self.offset() + start;
+		Some(unsafe { Self::from_raw_parts_mut(self.words_mut(), start, end - start) })
+	}
+	pub fn split_at(&self, mid: usize) -> (&Self, &Self) {
+		assert!(mid <= self.len(), "Split index out of bounds");
+		(&self[..mid], &self[mid..])
+	}
+	pub fn chunks(&self, chunk_len: usize) -> Chunks<'_, W> {
+		assert!(chunk_len > 0, "Chunk length must be nonzero");
+		Chunks { bits: self, chunk_len }
+	}
+
+	pub fn to_bit_vector(&self) -> GenericBitVector<Vec<W>> {
+		let len = self.len();
+		let words = (0..len).step_by(W::BITS).map(|start| self.load_word(start)).collect();
+		GenericBitVector { len, words }
+	}
+
+	fn offset(&self) -> usize {
+		self._bits.len() & (W::BITS - 1)
+	}
+	fn words(&self) -> *const W {
+		ptr::with_exposed_provenance((self as *const Self).addr())
+	}
+	fn words_mut(&mut self) -> *mut W {
+		ptr::with_exposed_provenance_mut((self as *mut Self).addr())
+	}
+	// Reads up to W::BITS bits starting at `start`, zero-filling past the end
+	pub(crate) fn load_word(&self, start: usize) -> W {
+		let bits = (self.len() - start).min(W::BITS);
+		if bits == 0 { return W::ZERO }
+
+		let bit = self.offset() + start;
+		let word_offset = bit & (W::BITS - 1);
+		let words = unsafe { self.words().add(bit >> W::LOG_BITS) };
+		let mut word = unsafe { *words } >> word_offset;
+		if word_offset + bits > W::BITS {
+			word |= unsafe { *words.add(1) } << (W::BITS - word_offset)
+		}
+		word & W::low_mask(bits)
+	}
+	// Overwrites `bits` bits starting at `start` with the low bits of `word`
+	pub(crate) fn store_word(&mut self, start: usize, word: W, bits: usize) {
+		debug_assert!(bits <= W::BITS && start + bits <= self.len());
+		if bits == 0 { return }
+
+		let mask = W::low_mask(bits);
+		let word = word & mask;
+		let bit = self.offset() + start;
+		let word_offset = bit & (W::BITS - 1);
+		unsafe {
+			let low_word = self.words_mut().add(bit >> W::LOG_BITS);
+			*low_word = *low_word & !(mask << word_offset) | word << word_offset;
+			if word_offset + bits > W::BITS {
+				let high_word = low_word.add(1);
+				let high_shift = W::BITS - word_offset;
+				*high_word = *high_word & !(mask >> high_shift) | word >> high_shift;
+			}
+		}
+	}
+	// Overwrites the bits starting at `start` with `bits`
+	pub(crate) fn store_bits(&mut self, start: usize, bits: &Self) {
+		for chunk_start in (0..bits.len()).step_by(W::BITS) {
+			let chunk_bits = (bits.len() - chunk_start).min(W::BITS);
+			self.store_word(start + chunk_start, bits.load_word(chunk_start), chunk_bits);
+		}
+	}
+	pub(crate) fn fill_bits(&mut self, start: usize, count: usize, value: bool) {
+		for chunk_start in (0..count).step_by(W::BITS) {
+			self.store_word(start + chunk_start, W::fill(value), (count - chunk_start).min(W::BITS));
+		}
+	}
+	// Calls `f(word, mask)` on each underlying word overlapping bits start..end,
+	// where `mask` selects the bits of the word in that range
+	pub(crate) fn for_each_word<F: FnMut(W, W)>(&self, start: usize, end: usize, mut f: F) {
+		Self::word_masks(self.offset() + start, self.offset() + end, |index, mask| {
+			f(unsafe { *self.words().add(index) }, mask)
+		})
+	}
+	// Replaces the bits selected by `mask` in each underlying word overlapping
+	// bits start..end with the corresponding bits of `f(word, mask)`
+	pub(crate) fn update_words<F: FnMut(W, W) -> W>(&mut self, start: usize, end: usize, mut f: F) {
+		let words = self.words_mut();
+		Self::word_masks(self.offset() + start, self.offset() + end, |index, mask| {
+			let word = unsafe { &mut *words.add(index) };
+			*word = *word & !mask | f(*word, mask) & mask;
+		})
+	}
+	fn word_masks<F: FnMut(usize, W)>(start: usize, end: usize, mut f: F) {
+		debug_assert!(start <= end);
+		if start == end { return }
+
+		let (first, last) = (start >> W::LOG_BITS, (end - 1) >> W::LOG_BITS);
+		let start_mask = !W::low_mask(start & (W::BITS - 1));
+		let end_mask = W::low_mask(end - (last << W::LOG_BITS));
+		if first == last { return f(first, start_mask & end_mask) }
+
+		f(first, start_mask);
+		for index in first + 1..last { f(index, W::ONES) }
+		f(last, end_mask);
+	}
+	// Copies `count` bits from `src` to `dest`, like ptr::copy() for bits
+	pub(crate) fn move_bits(&mut self, src: usize, dest: usize, count: usize) {
+		debug_assert!(src.max(dest) + count <= self.len());
+		let copy_chunk = |bits: &mut Self, start: usize| {
+			let chunk_bits = (count - start).min(W::BITS);
+			let word = bits.load_word(src + start);
+			bits.store_word(dest + start, word, chunk_bits);
+		};
+		// Copy in the direction that reads each source bit before overwriting it
+		if dest < src {
+			for start in (0..count).step_by(W::BITS) { copy_chunk(self, start) }
+		}
+		else if dest > src && count > 0 {
+			let last_start = (count - 1) / W::BITS * W::BITS;
+			for start in (0..=last_start).rev().step_by(W::BITS) { copy_chunk(self, start) }
+		}
+	}
+}
+
+pub struct Chunks<'a, W: Word = usize> {
+	bits: &'a BitSlice<W>,
+	chunk_len: usize,
+}
+
+impl<'a, W: Word> Iterator for Chunks<'a, W> {
+	type Item = &'a BitSlice<W>;
+
+	fn next(&mut self) -> Option<&'a BitSlice<W>> {
+		if self.bits.is_empty() { return None }
+
+		let (chunk, rest) = self.bits.split_at(self.chunk_len.min(self.bits.len()));
+		self.bits = rest;
+		Some(chunk)
+	}
+}
+
+impl<S: Storage> Deref for GenericBitVector<S> {
+	type Target = BitSlice<S::Word>;
+
+	fn deref(&self) -> &BitSlice<S::Word> {
+		unsafe { BitSlice::from_raw_parts(self.words.as_words().as_ptr(), 0, self.len) }
+	}
+}
+impl<S: StorageMut> DerefMut for GenericBitVector<S> {
+	fn deref_mut(&mut self) -> &mut BitSlice<S::Word> {
+		let words = self.words.as_words_mut().as_mut_ptr();
+		unsafe { BitSlice::from_raw_parts_mut(words, 0, self.len) }
+	}
+}
+
+impl<W: Word> From<&BitSlice<W>> for GenericBitVector<Vec<W>> {
+	fn from(bits: &BitSlice<W>) -> Self {
+		bits.to_bit_vector()
+	}
+}
+
+macro_rules! index_range {
+	($range:ty) => {
+		impl<W: Word> Index<$range> for BitSlice<W> {
+			type Output = BitSlice<W>;
+
+			fn index(&self, range: $range) -> &BitSlice<W> {
+				self.get_range(range).expect("Range out of bounds")
+			}
+		}
+		impl<W: Word> IndexMut<$range> for BitSlice<W> {
+			fn index_mut(&mut self, range: $range) -> &mut BitSlice<W> {
+				self.get_range_mut(range).expect("Range out of bounds")
+			}
+		}
+	};
+}
+
+index_range!(Range<usize>);
+index_range!(RangeFrom<usize>);
+index_range!(RangeFull);
+index_range!(RangeInclusive<usize>);
+index_range!(RangeTo<usize>);
+index_range!(RangeToInclusive<usize>);
+
+impl<'a, W: Word> IntoIterator for &'a BitSlice<W> {
+	type Item = bool;
+	type IntoIter = Iter<'a, W>;
+
+	fn into_iter(self) -> Iter<'a, W> {
+		Iter { bits: self, start: 0, end: self.len() }
+	}
+}
+
+impl<W: Word> Debug for BitSlice<W> {
+	fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+		fmt.debug_list().entries(self.iter().map(|b| b as u8)).finish()
+	}
+}
+
+impl<W: Word> PartialEq for BitSlice<W> {
+	fn eq(&self, other: &Self) -> bool {
+		self.len() == other.len() &&
+			(0..self.len()).step_by(W::BITS)
+				.all(|start| self.load_word(start) == other.load_word(start))
+	}
+}
+impl<W: Word> Eq for BitSlice<W> {}
+impl<S: Storage> PartialEq<GenericBitVector<S>> for BitSlice<S::Word> {
+	fn eq(&self, other: &GenericBitVector<S>) -> bool {
+		*self == **other
+	}
+}
+impl<S: Storage> PartialEq<BitSlice<S::Word>> for GenericBitVector<S> {
+	fn eq(&self, other: &BitSlice<S::Word>) -> bool {
+		**self == *other
+	}
+}
+impl<W: Word> Ord for BitSlice<W> {
+	fn cmp(&self, other: &Self) -> Ordering {
+		let common_len = self.len().min(other.len());
+		for start in (0..common_len).step_by(W::BITS) {
+			let compared_bits = W::low_mask(common_len - start);
+			let different_bits = (self.load_word(start) ^ other.load_word(start)) & compared_bits;
+			if different_bits != W::ZERO {
+				let index = start + different_bits.trailing_zeros() as usize;
+				return self.get(index).cmp(&other.get(index))
+			}
+		}
+		self.len().cmp(&other.len())
+	}
+}
+impl<W: Word> PartialOrd for BitSlice<W> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::iter::FromIterator;
+	use crate::BitVector;
+
+	fn pattern(len: usize) -> BitVector {
+		BitVector::from_iter((0..len).map(|i| (i * i + i / 3) % 5 < 2))
+	}
+
+	#[test]
+	fn test_get_set() {
+		let mut bits = pattern(300);
+		let values: Vec<_> = bits.iter().collect();
+		for start in 0..150 {
+			let slice = &bits[start..(start + 100)];
+			assert_eq!(slice.len(), 100);
+			for i in 0..100 { assert_eq!(slice.get(i), Some(values[start + i])) }
+			assert_eq!(slice.get(100), None);
+			assert!(slice.iter().eq(values[start..(start + 100)].iter().cloned()));
+		}
+		for start in 0..150 {
+			let slice = &mut bits[start..=(start + 99)];
+			for i in 0..100 { slice.set(i, i % 3 == 0).unwrap() }
+			assert_eq!(slice.set(100, true), None);
+			for (i, &value) in values.iter().enumerate() {
+				let expected =
+					if (start..(start + 100)).contains(&i) { (i - start) % 3 == 0 }
+					else { value };
+				assert_eq!(bits.get(i), Some(expected));
+			}
+			let restored = &mut bits[start..];
+			for (i, &value) in values[start..].iter().enumerate() { restored.set(i, value); }
+		}
+		assert!(bits.iter().eq(values));
+	}
+
+	#[test]
+	fn test_split_chunks() {
+		let bits = pattern(500);
+		let slice = &bits[7..];
+		for mid in 0..=slice.len() {
+			let (left, right) = slice.split_at(mid);
+			assert_eq!(left.len(), mid);
+			assert!(left.iter().chain(right).eq(slice));
+			assert_eq!(*left, bits[7..(7 + mid)]);
+		}
+		for chunk_len in 1..100 {
+			let chunks: Vec<_> = slice.chunks(chunk_len).collect();
+			assert_eq!(chunks.len(), slice.len().div_ceil(chunk_len));
+			for (i, chunk) in chunks.iter().enumerate() {
+				assert_eq!(**chunk, slice[(i * chunk_len)..][..chunk.len()]);
+				if i + 1 < chunks.len() { assert_eq!(chunk.len(), chunk_len) }
+			}
+			assert!(chunks.into_iter().flatten().eq(slice));
+		}
+	}
+
+	#[test]
+	fn test_compare() {
+		let bits = pattern(400);
+		for start in 0..70 {
+			for len in [0, 1, 63, 64, 65, 200].iter().cloned() {
+				let slice = &bits[start..(start + len)];
+				let copy = slice.to_bit_vector();
+				assert_eq!(copy.len(), len);
+				assert_eq!(*slice, copy);
+				assert_eq!(copy, *slice);
+				assert!(copy.iter().eq(slice));
+				let shifted = &bits[(start + 1)..(start + 1 + len)];
+				let values: Vec<_> = slice.iter().collect();
+				let shifted_values: Vec<_> = shifted.iter().collect();
+				assert_eq!(slice.cmp(shifted), values.cmp(&shifted_values));
+				assert_eq!(slice == shifted, values == shifted_values);
+				let prefix = &slice[..(len / 2)];
+				assert_eq!(prefix.cmp(slice), (len / 2).cmp(&len));
+			}
+		}
+	}
+
+	#[test]
+	fn test_deref() {
+		let mut bits = BitVector::new();
+		assert!(bits.iter().next().is_none());
+		assert_eq!(bits[..].len(), 0);
+		bits.extend(vec![true, false, true]);
+		assert_eq!(bits[1..].to_bit_vector(), BitVector::from_iter(vec![false, true]));
+		bits[1..].set(0, true);
+		assert_eq!(bits, BitVector::from_iter(vec![true; 3]));
+		assert_eq!(format!("{:?}", &bits[1..]), "[1, 1]");
+	}
+
+	#[test]
+	fn test_empty() {
+		// An empty slice at the end of the words must not read past them
+		let bits = pattern(64);
+		let empty = &bits[64..];
+		assert_eq!(empty.load_word(0), 0);
+		assert_eq!(*empty, bits[..0]);
+		assert!(*empty < bits[63..]);
+		assert!(empty.to_bit_vector().is_empty());
+		assert_eq!(format!("{:?}", empty), "[]");
+	}
+}
@@ -0,0 +1,338 @@
+use std::convert::TryFrom;
+use super::{BitSlice, BitVector, Word, WORD_BITS};
+
+const VALUE_BITS: usize = 64;
+const VARINT_GROUP_BITS: usize = 7;
+const VARINT_CONTINUE: u64 = 1 << VARINT_GROUP_BITS;
+
+// The number of bits needed to write `value` in binary, for value > 0
+fn bit_width(value: u64) -> usize {
+	VALUE_BITS - value.leading_zeros() as usize
+}
+
+// Reverses the low `width` bits of `value`
+fn reverse_low_bits(value: u64, width: usize) -> u64 {
+	if width == 0 { 0 } else { value.reverse_bits() >> (VALUE_BITS - width) }
+}
+
+// Appends fixed-width integers and universal codes to a BitVector.
+// write_bits() stores the least significant bit first, matching bytes(),
+// while the universal codes write their binary parts most significant bit first
+// so that their bit strings match the usual definitions.
+#[derive(Clone, Debug, Default)]
+pub struct BitWriter {
+	bits: BitVector,
+}
+
+impl BitWriter {
+	pub fn new() -> Self {
+		BitWriter::default()
+	}
+	pub fn len(&self) -> usize {
+		self.bits.len()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.bits.is_empty()
+	}
+	pub fn bits(&self) -> &BitVector {
+		&self.bits
+	}
+	pub fn into_bits(self) -> BitVector {
+		self.bits
+	}
+
+	pub fn write_bit(&mut self, bit: bool) {
+		self.bits.push(bit)
+	}
+	// Writes the low `width` bits of `value`, for width <= 64
+	pub fn write_bits(&mut self, value: u64, width: usize) {
+		assert!(width <= VALUE_BITS, "Width is too large");
+		for start in (0..width).step_by(WORD_BITS) {
+			self.bits.push_word((value >> start) as usize, (width - start).min(WORD_BITS));
+		}
+	}
+	// `count` zeros followed by a one
+	pub fn write_unary(&mut self, count: usize) {
+		for start in (0..count).step_by(WORD_BITS) {
+			self.bits.push_word(0, (count - start).min(WORD_BITS));
+		}
+		self.write_bit(true);
+	}
+	pub fn write_gamma(&mut self, value: u64) {
+		assert!(value > 0, "Elias gamma cannot encode 0");
+		let width = bit_width(value);
+		self.write_unary(width - 1);
+		self.write_msb_first(value, width - 1);
+	}
+	pub fn write_delta(&mut self, value: u64) {
+		assert!(value > 0, "Elias delta cannot encode 0");
+		let width = bit_width(value);
+		self.write_gamma(width as u64);
+		self.write_msb_first(value, width - 1);
+	}
+	// Exp-Golomb code of order `k`, which can encode 0.
+	// value + 2^k may take 65 bits, but its leading 1 is implied by the unary prefix.
+	pub fn write_exp_golomb(&mut self, value: u64, k: usize) {
+		assert!(k < VALUE_BITS, "Order is too large");
+		let shifted = value as u128 + (1 << k);
+		let width = 128 - shifted.leading_zeros() as usize;
+		self.write_unary(width - 1 - k);
+		self.write_msb_first(shifted as u64, width - 1);
+	}
+	// Golomb-Rice code with divisor 2^k: the quotient in unary, then the remainder.
+	// It takes (value >> k) + k + 1 bits, so k should keep the quotient small.
+	pub fn write_rice(&mut self, value: u64, k: usize) {
+		assert!(k < VALUE_BITS, "Parameter is too large");
+		let quotient = usize::try_from(value >> k).expect("Quotient is too large");
+		self.write_unary(quotient);
+		self.write_msb_first(value, k);
+	}
+	// LEB128: 7 bits per byte, least significant group first.
+	// When the writer is byte-aligned, bytes() gives the standard encoding.
+	pub fn write_varint(&mut self, mut value: u64) {
+		loop {
+			let group = value & (VARINT_CONTINUE - 1);
+			value >>= VARINT_GROUP_BITS;
+			if value == 0 {
+				self.write_bits(group, 8);
+				break
+			}
+			self.write_bits(group | VARINT_CONTINUE, 8);
+		}
+	}
+
+	// Writes the low `width` bits of `value`, most significant bit first
+	fn write_msb_first(&mut self, value: u64, width: usize) {
+		self.write_bits(reverse_low_bits(value, width), width)
+	}
+}
+
+// Reads back what a BitWriter wrote.
+// Reads that fail leave the position unchanged.
+#[derive(Clone, Debug)]
+pub struct BitReader<'a> {
+	bits: &'a BitSlice,
+	position: usize,
+}
+
+impl<'a> BitReader<'a> {
+	pub fn new(bits: &'a BitSlice) -> Self {
+		BitReader { bits, position: 0 }
+	}
+	pub fn position(&self) -> usize {
+		self.position
+	}
+	pub fn remaining(&self) -> usize {
+		self.bits.len() - self.position
+	}
+
+	pub fn read_bit(&mut self) -> Result<bool, &'static str> {
+		let bit = self.bits.get(self.position).ok_or("Unexpected end of stream")?;
+		self.position += 1;
+		Ok(bit)
+	}
+	pub fn read_bits(&mut self, width: usize) -> Result<u64, &'static str> {
+		assert!(width <= VALUE_BITS, "Width is too large");
+		if width > self.remaining() { return Err("Unexpected end of stream") }
+
+		let mut value = 0;
+		for start in (0..width).step_by(WORD_BITS) {
+			let word = self.bits.load_word(self.position + start);
+			value |= ((word & usize::low_mask((width - start).min(WORD_BITS))) as u64) << start;
+		}
+		self.position += width;
+		Ok(value)
+	}
+	pub fn read_unary(&mut self) -> Result<usize, &'static str> {
+		let one = self.bits.next_one(self.position).ok_or("Unexpected end of stream")?;
+		let count = one - self.position;
+		self.position = one + 1;
+		Ok(count)
+	}
+	pub fn read_gamma(&mut self) -> Result<u64, &'static str> {
+		self.restore_on_error(|reader| {
+			let width = reader.read_unary()? + 1;
+			if width > VALUE_BITS { return Err("Code is too long") }
+			Ok(1 << (width - 1) | reader.read_msb_first(width - 1)?)
+		})
+	}
+	pub fn read_delta(&mut self) -> Result<u64, &'static str> {
+		self.restore_on_error(|reader| {
+			let width = reader.read_gamma()? as usize;
+			if width > VALUE_BITS { return Err("Code is too long") }
+			Ok(1 << (width - 1) | reader.read_msb_first(width - 1)?)
+		})
+	}
+	pub fn read_exp_golomb(&mut self, k: usize) -> Result<u64, &'static str> {
+		assert!(k < VALUE_BITS, "Order is too large");
+		self.restore_on_error(|reader| {
+			let width = reader.read_unary()? + 1 + k;
+			if width > VALUE_BITS + 1 { return Err("Code is too long") }
+			let shifted = 1 << (width - 1) | reader.read_msb_first(width - 1)? as u128;
+			u64::try_from(shifted - (1 << k)).map_err(|_| "Code is too long")
+		})
+	}
+	pub fn read_rice(&mut self, k: usize) -> Result<u64, &'static str> {
+		assert!(k < VALUE_BITS, "Parameter is too large");
+		self.restore_on_error(|reader| {
+			let quotient = reader.read_unary()? as u64;
+			if quotient > u64::MAX >> k { return Err("Code is too long") }
+			Ok(quotient << k | reader.read_msb_first(k)?)
+		})
+	}
+	pub fn read_varint(&mut self) -> Result<u64, &'static str> {
+		self.restore_on_error(|reader| {
+			let mut value = 0;
+			for shift in (0..VALUE_BITS).step_by(VARINT_GROUP_BITS) {
+				let byte = reader.read_bits(8)?;
+				let group = byte & (VARINT_CONTINUE - 1);
+				if group << shift >> shift != group { return Err("Varint is too long") }
+				value |= group << shift;
+				if byte & VARINT_CONTINUE == 0 { return Ok(value) }
+			}
+			Err("Varint is too long")
+		})
+	}
+
+	fn read_msb_first(&mut self, width: usize) -> Result<u64, &'static str> {
+		Ok(reverse_low_bits(self.read_bits(width)?, width))
+	}
+	fn restore_on_error<T, F>(&mut self, read: F) -> Result<T, &'static str>
+		where F: FnOnce(&mut Self) -> Result<T, &'static str>
+	{
+		let position = self.position;
+		let result = read(self);
+		if result.is_err() { self.position = position }
+		result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const VALUES: [u64; 12] =
+		[0, 1, 2, 3, 7, 8, 100, 1000, 65_535, 1 << 40, u64::MAX >> 1, u64::MAX];
+
+	fn bit_string(bits: &BitSlice) -> String {
+		bits.iter().map(|bit| if bit { '1' } else { '0' }).collect()
+	}
+
+	#[test]
+	fn test_fixed_width() {
+		let mut writer = BitWriter::new();
+		for width in 0..=64 {
+			for &value in &VALUES { writer.write_bits(value, width) }
+			writer.write_bit(width % 3 == 0);
+		}
+		let bits = writer.into_bits();
+		let mut reader = BitReader::new(&bits);
+		for width in 0..=64 {
+			let mask = if width == 64 { u64::MAX } else { (1 << width) - 1 };
+			for &value in &VALUES { assert_eq!(reader.read_bits(width), Ok(value & mask)) }
+			assert_eq!(reader.read_bit(), Ok(width % 3 == 0));
+		}
+		assert_eq!(reader.remaining(), 0);
+		assert_eq!(reader.read_bit(), Err("Unexpected end of stream"));
+		assert_eq!(reader.read_bits(1), Err("Unexpected end of stream"));
+		assert_eq!(reader.read_bits(0), Ok(0));
+
+		let mut writer = BitWriter::new();
+		writer.write_bits(0b1101, 4);
+		assert_eq!(bit_string(writer.bits()), "1011");
+	}
+
+	#[test]
+	fn test_known_codes() {
+		let encode = |write: &dyn Fn(&mut BitWriter)| {
+			let mut writer = BitWriter::new();
+			write(&mut writer);
+			bit_string(writer.bits())
+		};
+		assert_eq!(encode(&|w| w.write_gamma(1)), "1");
+		assert_eq!(encode(&|w| w.write_gamma(5)), "00101");
+		assert_eq!(encode(&|w| w.write_delta(1)), "1");
+		assert_eq!(encode(&|w| w.write_delta(10)), "00100010");
+		assert_eq!(encode(&|w| w.write_exp_golomb(0, 0)), "1");
+		assert_eq!(encode(&|w| w.write_exp_golomb(3, 0)), "00100");
+		assert_eq!(encode(&|w| w.write_exp_golomb(3, 2)), "111");
+		// u64::MAX + 1 needs 65 bits
+		let max_code = |zeros| "0".repeat(zeros) + "1" + &"0".repeat(64);
+		assert_eq!(encode(&|w| w.write_exp_golomb(u64::MAX, 0)), max_code(64));
+		assert_eq!(encode(&|w| w.write_exp_golomb(u64::MAX - 7, 3)), max_code(61));
+		assert_eq!(encode(&|w| w.write_rice(11, 2)), "00111");
+		let mut writer = BitWriter::new();
+		writer.write_varint(624_485);
+		assert_eq!(writer.bits().bytes().collect::<Vec<_>>(), [0xE5, 0x8E, 0x26]);
+	}
+
+	#[test]
+	fn test_round_trip() {
+		let mut writer = BitWriter::new();
+		for &value in &VALUES {
+			if value > 0 {
+				writer.write_gamma(value);
+				writer.write_delta(value);
+			}
+			for k in 0..5 { writer.write_exp_golomb(value >> 1, k) }
+			for &k in &[0, 3, 63] { writer.write_exp_golomb(value, k) }
+			writer.write_rice(value % 1000, 3);
+			writer.write_rice(value, 60);
+			writer.write_varint(value);
+		}
+		let bits = writer.into_bits();
+		let mut reader = BitReader::new(&bits);
+		for &value in &VALUES {
+			if value > 0 {
+				assert_eq!(reader.read_gamma(), Ok(value));
+				assert_eq!(reader.read_delta(), Ok(value));
+			}
+			for k in 0..5 { assert_eq!(reader.read_exp_golomb(k), Ok(value >> 1)) }
+			for &k in &[0, 3, 63] { assert_eq!(reader.read_exp_golomb(k), Ok(value)) }
+			assert_eq!(reader.read_rice(3), Ok(value % 1000));
+			assert_eq!(reader.read_rice(60), Ok(value));
+			assert_eq!(reader.read_varint(), Ok(value));
+		}
+		assert_eq!(reader.remaining(), 0);
+
+		// A quotient far longer than a word
+		let mut writer = BitWriter::new();
+		writer.write_rice(1 << 20 | 5, 3);
+		writer.write_rice(u64::MAX, 44);
+		assert_eq!(writer.len(), (1 << 17) + 4 + (1 << 20) - 1 + 45);
+		let bits = writer.into_bits();
+		let mut reader = BitReader::new(&bits);
+		assert_eq!(reader.read_rice(3), Ok(1 << 20 | 5));
+		assert_eq!(reader.read_rice(44), Ok(u64::MAX));
+	}
+
+	#[test]
+	fn test_errors() {
+		let mut writer = BitWriter::new();
+		writer.write_delta(1000);
+		writer.write_varint(1 << 50);
+		let bits = writer.into_bits();
+		for len in 0..bits.len() {
+			let mut reader = BitReader::new(&bits[..len]);
+			if reader.read_delta().is_ok() {
+				assert_eq!(reader.read_varint(), Err("Unexpected end of stream"));
+			}
+			else { assert_eq!(reader.position(), 0) }
+		}
+
+		// Codes whose values do not fit in a u64
+		let mut writer = BitWriter::new();
+		writer.write_unary(64);
+		writer.write_bits(0, 64);
+		let bits = writer.into_bits();
+		assert_eq!(BitReader::new(&bits).read_gamma(), Err("Code is too long"));
+		assert_eq!(BitReader::new(&bits).read_exp_golomb(1), Err("Code is too long"));
+		let mut writer = BitWriter::new();
+		for _ in 0..10 { writer.write_bits(0xFF, 8) }
+		writer.write_bits(0x01, 8);
+		let bits = writer.into_bits();
+		let mut reader = BitReader::new(&bits);
+		assert_eq!(reader.read_varint(), Err("Varint is too long"));
+		assert_eq!(reader.position(), 0);
+	}
+}
@@ -0,0 +1,194 @@
+use std::ops::RangeBounds;
+use super::{range_bounds, BitVector, RankSelect};
+
+// A wavelet matrix: level l holds bit l (counting from the most significant)
+// of every symbol, with the symbols stably sorted by that bit afterwards.
+// Each query follows one symbol or range down the levels with rank,
+// or back up with select, so it takes time proportional to the symbol width.
+pub struct WaveletMatrix {
+	len: usize,
+	levels: Vec<Level>,
+}
+
+struct Level {
+	bits: RankSelect,
+	// The number of symbols with a 0 at this level, which come first in the next level
+	zeros: usize,
+}
+
+impl Level {
+	// Where position `index` goes in the next level if its bit is `bit`
+	fn next_position(&self, index: usize, bit: bool) -> usize {
+		if bit { self.zeros + self.bits.rank1(index).unwrap() }
+		else { self.bits.rank0(index).unwrap() }
+	}
+}
+
+impl WaveletMatrix {
+	pub fn new(symbols: &[u32]) -> Self {
+		let max = symbols.iter().cloned().max().unwrap_or(0);
+		let width = u32::BITS - max.leading_zeros();
+		let mut symbols = symbols.to_vec();
+		let levels = (0..width).rev().map(|shift| {
+			let bits: BitVector = symbols.iter().map(|symbol| symbol >> shift & 1 != 0).collect();
+			let (mut next, ones): (Vec<_>, Vec<_>) =
+				symbols.iter().partition(|&&symbol| symbol >> shift & 1 == 0);
+			let zeros = next.len();
+			next.extend(ones);
+			symbols = next;
+			Level { bits: RankSelect::new(bits), zeros }
+		}).collect();
+		WaveletMatrix { len: symbols.len(), levels }
+	}
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	pub fn access(&self, mut index: usize) -> Option<u32> {
+		if index >= self.len { return None }
+
+		let mut symbol = 0;
+		for level in &self.levels {
+			let bit = level.bits.bits().get(index).unwrap();
+			symbol = symbol << 1 | bit as u32;
+			index = level.next_position(index, bit);
+		}
+		Some(symbol)
+	}
+	// The number of occurrences of `symbol` before `index`
+	pub fn rank(&self, symbol: u32, index: usize) -> Option<usize> {
+		if index > self.len { return None }
+		if !self.in_alphabet(symbol) { return Some(0) }
+
+		let (mut start, mut end) = (0, index);
+		for (level, bit) in self.levels.iter().zip(self.symbol_bits(symbol)) {
+			start = level.next_position(start, bit);
+			end = level.next_position(end, bit);
+		}
+		Some(end - start)
+	}
+	// The index of the occurrence of `symbol` preceded by `rank` others
+	pub fn select(&self, symbol: u32, rank: usize) -> Option<usize> {
+		if !self.in_alphabet(symbol) { return None }
+
+		// Find where the symbol's occurrences end up in the last level
+		let (mut start, mut end) = (0, self.len);
+		for (level, bit) in self.levels.iter().zip(self.symbol_bits(symbol)) {
+			start = level.next_position(start, bit);
+			end = level.next_position(end, bit);
+		}
+		if rank >= end - start { return None }
+
+		// Then follow that occurrence back up
+		let mut index = start + rank;
+		for (level, bit) in self.levels.iter().zip(self.symbol_bits(symbol)).rev() {
+			index =
+				if bit { level.bits.select1(index - level.zeros) }
+				else { level.bits.select0(index) }.unwrap();
+		}
+		Some(index)
+	}
+	// The symbol with the given rank (from 0) if symbols[range] were sorted,
+	// e.g. rank (len - 1) / 2 gives the median
+	pub fn quantile<R: RangeBounds<usize>>(&self, range: R, mut rank: usize) -> Option<u32> {
+		let (mut start, mut end) = range_bounds(range, self.len)?;
+		if rank >= end - start { return None }
+
+		let mut symbol = 0;
+		for level in &self.levels {
+			let zeros = level.bits.rank0(end).unwrap() - level.bits.rank0(start).unwrap();
+			let bit = rank >= zeros;
+			if bit { rank -= zeros }
+			symbol = symbol << 1 | bit as u32;
+			start = level.next_position(start, bit);
+			end = level.next_position(end, bit);
+		}
+		Some(symbol)
+	}
+	pub fn iter(&self) -> impl Iterator<Item=u32> + '_ {
+		(0..self.len).map(move |index| self.access(index).unwrap())
+	}
+
+	fn in_alphabet(&self, symbol: u32) -> bool {
+		self.levels.len() == u32::BITS as usize || symbol >> self.levels.len() == 0
+	}
+	// The bits of `symbol` used at each level, most significant first
+	fn symbol_bits(&self, symbol: u32)
+		-> impl DoubleEndedIterator<Item=bool> + ExactSizeIterator
+	{
+		(0..self.levels.len()).rev().map(move |shift| symbol >> shift & 1 != 0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// A deterministic xorshift sequence
+	fn pseudo_random_symbols(len: usize, alphabet: u32) -> Vec<u32> {
+		let mut state = 0x2545_F491u32;
+		(0..len).map(|_| {
+			state ^= state << 13;
+			state ^= state >> 17;
+			state ^= state << 5;
+			state % alphabet
+		}).collect()
+	}
+
+	fn check_against_scan(symbols: &[u32]) {
+		let matrix = WaveletMatrix::new(symbols);
+		assert_eq!(matrix.len(), symbols.len());
+		assert!(matrix.iter().eq(symbols.iter().cloned()));
+		assert_eq!(matrix.access(symbols.len()), None);
+		// Every symbol present, plus small ones that may be absent
+		let max = symbols.iter().cloned().max().unwrap_or(0);
+		for symbol in (0..=max.min(400) + 1).chain(symbols.iter().cloned()) {
+			let positions: Vec<_> =
+				(0..symbols.len()).filter(|&i| symbols[i] == symbol).collect();
+			for (rank, &position) in positions.iter().enumerate() {
+				assert_eq!(matrix.select(symbol, rank), Some(position));
+				assert_eq!(matrix.rank(symbol, position), Some(rank));
+			}
+			assert_eq!(matrix.select(symbol, positions.len()), None);
+			assert_eq!(matrix.rank(symbol, symbols.len()), Some(positions.len()));
+		}
+		assert_eq!(matrix.rank(0, symbols.len() + 1), None);
+	}
+
+	#[test]
+	fn test_queries() {
+		check_against_scan(&[]);
+		check_against_scan(&[0, 0, 0]);
+		check_against_scan(&[3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5]);
+		check_against_scan(&pseudo_random_symbols(1000, 7));
+		check_against_scan(&pseudo_random_symbols(2000, 300));
+	}
+
+	#[test]
+	fn test_quantile() {
+		let symbols = pseudo_random_symbols(300, 50);
+		let matrix = WaveletMatrix::new(&symbols);
+		for start in (0..300).step_by(13) {
+			for end in (start..=300).step_by(17) {
+				let mut sorted = symbols[start..end].to_vec();
+				sorted.sort_unstable();
+				for (rank, &symbol) in sorted.iter().enumerate() {
+					assert_eq!(matrix.quantile(start..end, rank), Some(symbol));
+				}
+				assert_eq!(matrix.quantile(start..end, sorted.len()), None);
+			}
+		}
+		assert_eq!(matrix.quantile(0..301, 0), None);
+	}
+
+	#[test]
+	fn test_full_width() {
+		let symbols = [u32::MAX, 0, 1 << 31, u32::MAX, 12345];
+		check_against_scan(&symbols);
+		let matrix = WaveletMatrix::new(&symbols);
+		assert_eq!(matrix.quantile(.., 2), Some(1 << 31));
+		assert_eq!(matrix.rank(u32::MAX, 5), Some(2));
+	}
+}
@@ -0,0 +1,137 @@
+use std::fmt::Debug;
+use std::ops::{
+	BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr, Sub,
+};
+
+// Unsafe code relies on these traits behaving, so only this crate can implement them
+mod sealed {
+	pub trait Sealed {}
+}
+use sealed::Sealed;
+
+// An unsigned integer type that bits can be packed into
+pub trait Word: Sealed +
+	Copy + Debug + Default + Eq +
+	BitAnd<Output=Self> + BitOr<Output=Self> + BitXor<Output=Self> + Not<Output=Self> +
+	BitAndAssign + BitOrAssign + BitXorAssign +
+	Shl<usize, Output=Self> + Shr<usize, Output=Self> + Sub<Output=Self>
+{
+	const BITS: usize;
+	const LOG_BITS: u32;
+	const ZERO: Self;
+	const ONE: Self;
+	const ONES: Self;
+
+	fn count_ones(self) -> u32;
+	fn leading_zeros(self) -> u32;
+	fn trailing_zeros(self) -> u32;
+	fn from_bool(value: bool) -> Self;
+	fn from_u8(byte: u8) -> Self;
+	// The lowest 8 bits
+	fn low_u8(self) -> u8;
+
+	// A word whose lowest `bits` bits are ones, for bits <= BITS
+	fn low_mask(bits: usize) -> Self {
+		if bits < Self::BITS { (Self::ONE << bits) - Self::ONE } else { Self::ONES }
+	}
+	fn fill(value: bool) -> Self {
+		if value { Self::ONES } else { Self::ZERO }
+	}
+}
+
+macro_rules! impl_word {
+	($($word:ty),*) => {
+		$(
+			impl Sealed for $word {}
+			impl Word for $word {
+				const BITS: usize = <$word>::BITS as usize;
+				const LOG_BITS: u32 = <$word>::BITS.trailing_zeros();
+				const ZERO: Self = 0;
+				const ONE: Self = 1;
+				const ONES: Self = !0;
+
+				fn count_ones(self) -> u32 {
+					<$word>::count_ones(self)
+				}
+				fn leading_zeros(self) -> u32 {
+					<$word>::leading_zeros(self)
+				}
+				fn trailing_zeros(self) -> u32 {
+					<$word>::trailing_zeros(self)
+				}
+				fn from_bool(value: bool) -> Self {
+					value as Self
+				}
+				fn from_u8(byte: u8) -> Self {
+					byte as Self
+				}
+				fn low_u8(self) -> u8 {
+					self as u8
+				}
+			}
+		)*
+	};
+}
+
+impl_word!(u8, u16, u32, u64, u128, usize);
+
+// Where the words of a GenericBitVector live
+pub trait Storage: Sealed {
+	type Word: Word;
+
+	fn as_words(&self) -> &[Self::Word];
+}
+pub trait StorageMut: Storage {
+	fn as_words_mut(&mut self) -> &mut [Self::Word];
+}
+
+impl<W: Word> Sealed for Vec<W> {}
+impl<W: Word> Storage for Vec<W> {
+	type Word = W;
+
+	fn as_words(&self) -> &[W] {
+		self
+	}
+}
+impl<W: Word> StorageMut for Vec<W> {
+	fn as_words_mut(&mut self) -> &mut [W] {
+		self
+	}
+}
+
+impl<W: Word> Sealed for &[W] {}
+impl<W: Word> Storage for &[W] {
+	type Word = W;
+
+	fn as_words(&self) -> &[W] {
+		self
+	}
+}
+
+impl<W: Word> Sealed for &mut [W] {}
+impl<W: Word> Storage for &mut [W] {
+	type Word = W;
+
+	fn as_words(&self) -> &[W] {
+		self
+	}
+}
+impl<W: Word> StorageMut for &mut [W] {
+	fn as_words_mut(&mut self) -> &mut [W] {
+		self
+	}
+}
+
+impl<W: Word, const N: usize> Sealed for [W; N] {}
+impl<W: Word, const N: usize> Storage for [W; N] {
+	type Word = W;
+
+	fn as_words(&self) -> &[W] {
+		self
+	}
+}
+impl<W: Word, const N: usize> StorageMut for [W; N] {
+	fn as_words_mut(&mut self) -> &mut [W] {
+		self
+	}
+}
@@ -1,3 +1,13 @@
+mod linear;
+mod patch;
+mod unified;
+
+pub use linear::diff_linear;
+pub use patch::{
+	apply_unified, parse_patch, parse_patches, report, Hunk, HunkLine, HunkResult, Patch,
+};
+pub use unified::unified_diff;
+
 struct DiagonalResult {
 	insertion: bool,
 	start_b_index: usize,
@@ -52,7 +62,7 @@ fn make_diff<'b, T: PartialEq>(
 		if frontiers.is_empty() { break }
 	}
 	diff.reverse();
-	return diff;
+	diff
 }
 
 pub fn diff<'b, T: PartialEq>(a: &[T], b: &'b [T]) -> Vec<DiffElement<'b, T>> {
@@ -185,7 +195,7 @@ mod tests {
 					let inserted = &b[..1];
 					match diff_rest_right.get_mut(0) {
 						Some(Change(_, ref mut slice)) =>
-							*slice = join_slices(inserted, *slice),
+							*slice = join_slices(inserted, slice),
 						_ => diff_rest_right.insert(0, Change(0, inserted)),
 					}
 					diff_rest_right
@@ -204,6 +214,7 @@ mod tests {
 			let diff_result = diff(&items, &items);
 			assert_eq!(diff_result, [Same(i)]);
 			assert_eq!(diff_result, diff_brute(&items, &items));
+			assert_eq!(diff_linear(&items, &items), diff_result);
 			assert_eq!(apply_patch(&items, &diff_result), items);
 		}
 	}
@@ -233,6 +244,7 @@ mod tests {
 				let diff_result = diff(&initial, &inserted);
 				assert_eq!(diff_result, target_diff);
 				assert_eq!(diff_result, diff_brute(&initial, &inserted));
+				assert_eq!(diff_linear(&initial, &inserted), diff_result);
 				assert_eq!(apply_patch(&initial, &diff_result), inserted);
 			}
 		}
@@ -262,6 +274,7 @@ mod tests {
 				let diff_result = diff(&initial, &deleted);
 				assert_eq!(diff_result, target_diff);
 				assert_eq!(diff_result, diff_brute(&initial, &deleted));
+				assert_eq!(diff_linear(&initial, &deleted), diff_result);
 				assert_eq!(apply_patch(&initial, &diff_result), deleted);
 			}
 		}
@@ -275,12 +288,15 @@ mod tests {
 		let diff_result = diff(&a, &b);
 		assert_eq!(diff_len(&diff_result), 5);
 		assert_eq!(apply_patch(&a, &diff_result), b);
+		let diff_result = diff_linear(&a, &b);
+		assert_eq!(diff_len(&diff_result), 5);
+		assert_eq!(apply_patch(&a, &diff_result), b);
 
 		// From https://en.wikipedia.org/wiki/Diff#Algorithm
-		let diff_result = diff(
-			&['a', 'b', 'c', 'd',      'f', 'g', 'h', 'j', 'q',                'z'],
-			&['a', 'b', 'c', 'd', 'e', 'f', 'g', 'i', 'j', 'k', 'r', 'x', 'y', 'z'],
-		);
+		let a = ['a', 'b', 'c', 'd',      'f', 'g', 'h', 'j', 'q',                'z'];
+		let b = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'i', 'j', 'k', 'r', 'x', 'y', 'z'];
+		let diff_result = diff(&a, &b);
+		assert_eq!(diff_linear(&a, &b), diff_result);
 		assert_eq!(diff_result, [
 			Same(4),
 			Change(0, &['e']),
@@ -349,6 +365,7 @@ important new additions
 to this document."
 		);
 		let diff_result = diff(&original, &new);
+		assert_eq!(diff_linear(&original, &new), diff_result);
 		assert_eq!(diff_result, [
 			Change(0, &to_lines(
 "This is an important
@@ -372,4 +389,49 @@ to this document."
 		]);
 		assert_eq!(apply_patch(&original, &diff_result), new);
 	}
+
+	// A deterministic xorshift sequence over a small alphabet, so elements repeat
+	fn pseudo_random(len: usize, alphabet: u32, seed: u32) -> Vec<u32> {
+		let mut state = 0x2545_F491 ^ seed;
+		(0..len).map(|_| {
+			state ^= state << 13;
+			state ^= state >> 17;
+			state ^= state << 5;
+			state % alphabet
+		}).collect()
+	}
+
+	// The length of a shortest edit script, from the longest common subsequence
+	fn edit_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+		let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+		for (i, a_elem) in a.iter().enumerate() {
+			for (j, b_elem) in b.iter().enumerate() {
+				lcs[i + 1][j + 1] =
+					if a_elem == b_elem { lcs[i][j] + 1 } else { lcs[i][j + 1].max(lcs[i + 1][j]) };
+			}
+		}
+		a.len() + b.len() - 2 * lcs[a.len()][b.len()]
+	}
+
+	#[test]
+	fn test_linear_space() {
+		for seed in 0..2000 {
+			let alphabet = seed % 5 + 1;
+			let a = pseudo_random(seed as usize % 17, alphabet, seed);
+			let b = pseudo_random(seed as usize / 17 % 17, alphabet, seed + 3000);
+			let diff_result = diff_linear(&a, &b);
+			assert_eq!(diff_result, diff(&a, &b), "{:?} {:?}", a, b);
+			assert_eq!(diff_len(&diff_result), edit_distance(&a, &b));
+		}
+		// Ties that a search from both ends would break differently
+		let (a, b) = ([1, 2, 2, 1, 1, 2, 1, 1], [2]);
+		assert_eq!(diff_linear(&a, &b), diff(&a, &b));
+		for &(a_len, b_len, alphabet) in &[(500, 700, 4), (1000, 900, 50), (2000, 0, 2)] {
+			let a = pseudo_random(a_len, alphabet, 1);
+			let b = pseudo_random(b_len, alphabet, 2);
+			let diff_result = diff_linear(&a, &b);
+			assert_eq!(diff_result, diff(&a, &b));
+			assert_eq!(apply_patch(&a, &diff_result), b);
+		}
+	}
 }
\ No newline at end of file
@@ -0,0 +1,131 @@
+use super::DiffElement;
+
+// Accumulates a diff, merging adjacent elements of the same kind
+struct DiffBuilder<'b, T> {
+	b: &'b [T],
+	diff: Vec<DiffElement<'b, T>>,
+}
+
+impl<'b, T> DiffBuilder<'b, T> {
+	fn same(&mut self, count: usize) {
+		use DiffElement::*;
+
+		if count == 0 { return }
+
+		match self.diff.last_mut() {
+			Some(Same(last_count)) => *last_count += count,
+			_ => self.diff.push(Same(count)),
+		}
+	}
+	// Deletes `deletions` elements of a and inserts b[b_start..b_end]
+	fn change(&mut self, deletions: usize, b_start: usize, b_end: usize) {
+		use DiffElement::*;
+
+		if deletions == 0 && b_start == b_end { return }
+
+		// The previous insertions, if any, end at b_start
+		match self.diff.last_mut() {
+			Some(Change(last_deletions, insertions)) => {
+				let insertions_start = b_start - insertions.len();
+				*last_deletions += deletions;
+				*insertions = &self.b[insertions_start..b_end];
+			},
+			_ => self.diff.push(Change(deletions, &self.b[b_start..b_end])),
+		}
+	}
+}
+
+// The furthest point reached on a diagonal, as in diff(),
+// and where the path to it was after `half` edits
+#[derive(Clone, Copy)]
+struct Reach {
+	end_b_index: usize,
+	middle: (usize, usize),
+}
+
+// Runs diff()'s forward search, keeping only the last frontier.
+// Diagonal i of frontier d holds the paths with d edits, i of them insertions.
+// Returns the edit distance and the (a, b) point where the path diff() takes
+// ends its snake after `half` edits, or the end if the distance is at most `half`.
+fn forward_search<T: PartialEq>(
+	a: &[T],
+	b: &[T],
+	half: usize,
+) -> (usize, (usize, usize)) {
+	let mut last_frontier: Vec<Reach> = vec![];
+	for diff_length in 0.. {
+		let mut frontier = Vec::with_capacity(diff_length + 1);
+		let mut insert = Reach { end_b_index: 0, middle: (0, 0) };
+		for diagonal in 0..=diff_length {
+			let delete = last_frontier.get(diagonal).cloned()
+				.unwrap_or(Reach { end_b_index: 0, middle: (0, 0) });
+			// Ties go to the deletion, as in diff()
+			let start = if insert.end_b_index > delete.end_b_index { insert } else { delete };
+			let mut end_a_index = start.end_b_index + diff_length - (diagonal << 1);
+			let mut end_b_index = start.end_b_index;
+			let done = loop {
+				match (a.get(end_a_index), b.get(end_b_index)) {
+					(Some(a_elem), Some(b_elem)) if a_elem == b_elem => {
+						end_a_index += 1;
+						end_b_index += 1;
+					},
+					(None, None) => break true,
+					_ => break false,
+				}
+			};
+			let middle =
+				if diff_length <= half { (end_a_index, end_b_index) } else { start.middle };
+			if done { return (diff_length, middle) }
+
+			frontier.push(Reach { end_b_index, middle });
+			insert = Reach { end_b_index: delete.end_b_index + 1, middle: delete.middle };
+		}
+		last_frontier = frontier;
+	}
+	unreachable!()
+}
+
+// Appends the diff of a and b[b_start..b_end], whose edit distance is `distance`.
+// diff() takes the same path from the start to any point on its path as it does
+// when diffing the sequences up to that point, and likewise from that point to the end,
+// so the path can be split at its middle and each half found separately.
+fn diff_range<'b, T: PartialEq>(
+	builder: &mut DiffBuilder<'b, T>,
+	a: &[T],
+	b_start: usize,
+	b_end: usize,
+	distance: usize,
+) {
+	let b = &builder.b[b_start..b_end];
+	if distance == 0 {
+		builder.same(a.len());
+	}
+	else if a.is_empty() || b.is_empty() {
+		builder.change(a.len(), b_start, b_end);
+	}
+	else if distance == 1 {
+		// The common prefix, then one deletion or insertion
+		let prefix = a.iter().zip(b).take_while(|(a_elem, b_elem)| a_elem == b_elem).count();
+		let deletions = (a.len() > b.len()) as usize;
+		let insertions = 1 - deletions;
+		builder.same(prefix);
+		builder.change(deletions, b_start + prefix, b_start + prefix + insertions);
+		builder.same(a.len() - prefix - deletions);
+	}
+	else {
+		let half = distance / 2;
+		let (_, (a_middle, b_middle)) = forward_search(a, b, half);
+		let b_middle = b_start + b_middle;
+		diff_range(builder, &a[..a_middle], b_start, b_middle, half);
+		diff_range(builder, &a[a_middle..], b_middle, b_end, distance - half);
+	}
+}
+
+// Like diff(), and gives the same result, but uses O(D) space instead of O(D^2),
+// where D is the edit distance, by recursively splitting the path at its middle.
+pub fn diff_linear<'b, T: PartialEq>(a: &[T], b: &'b [T]) -> Vec<DiffElement<'b, T>> {
+	let mut builder = DiffBuilder { b, diff: vec![] };
+	let (distance, _) = forward_search(a, b, 0);
+	diff_range(&mut builder, a, 0, b.len(), distance);
+	builder.diff
+}
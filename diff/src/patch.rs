@@ -0,0 +1,464 @@
+use std::fmt::Write;
+use std::iter::Peekable;
+
+// A unified diff of one file, as read by parse_patch()
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Patch {
+	pub old_name: Box<str>,
+	pub new_name: Box<str>,
+	pub hunks: Vec<Hunk>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Hunk {
+	// Line numbers from the "@@ -old_start,len +new_start,len @@" header.
+	// For an empty range, this is the line before it.
+	pub old_start: usize,
+	pub new_start: usize,
+	pub lines: Vec<HunkLine>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HunkLine {
+	Context(Box<str>),
+	Delete(Box<str>),
+	Insert(Box<str>),
+}
+
+impl Hunk {
+	// The lines the hunk expects to find in the old file
+	fn old_lines(&self) -> impl Iterator<Item=&str> {
+		use HunkLine::*;
+
+		self.lines.iter().filter_map(|line| match line {
+			Context(line) | Delete(line) => Some(&**line),
+			Insert(_) => None,
+		})
+	}
+	// The number of context lines before the first change and after the last
+	fn context_lens(&self) -> (usize, usize) {
+		let is_context = |line: &&HunkLine| matches!(line, HunkLine::Context(_));
+		let leading = self.lines.iter().take_while(is_context).count();
+		let trailing = self.lines.iter().rev().take_while(is_context).count();
+		// A hunk of only context has nothing to separate leading from trailing
+		if leading == self.lines.len() { (leading, 0) } else { (leading, trailing) }
+	}
+	// The 0-based index of the first old line
+	fn old_index(&self) -> usize {
+		if self.old_lines().next().is_none() { self.old_start }
+		else { self.old_start.saturating_sub(1) }
+	}
+}
+
+// A hunk's start line and line count
+type HunkRange = (usize, usize);
+
+// Parses "start" or "start,len"
+fn parse_range(range: &str) -> Result<HunkRange, &'static str> {
+	let (start, len) = match range.split_once(',') {
+		Some((start, len)) => (start, len.parse()),
+		None => (range, Ok(1)),
+	};
+	match (start.parse(), len) {
+		(Ok(start), Ok(len)) => Ok((start, len)),
+		_ => Err("Invalid hunk range"),
+	}
+}
+
+fn parse_hunk_header(header: &str) -> Result<(HunkRange, HunkRange), &'static str> {
+	let ranges = header.strip_prefix("@@ -")
+		.and_then(|rest| rest.split_once(" @@"))
+		.and_then(|(ranges, _)| ranges.split_once(" +"));
+	let (old_range, new_range) = ranges.ok_or("Invalid hunk header")?;
+	Ok((parse_range(old_range)?, parse_range(new_range)?))
+}
+
+// Strips the "--- " or "+++ " prefix and any tab-separated timestamp
+fn parse_file_name(line: Option<&str>, prefix: &str) -> Option<Box<str>> {
+	let name = line?.strip_prefix(prefix)?;
+	Some(name.split('\t').next().unwrap().into())
+}
+
+// Parses one file's headers and hunks, stopping before the first line
+// after them that is not a hunk header
+fn parse_file<'a, I: Iterator<Item=&'a str>>(lines: &mut Peekable<I>)
+	-> Result<Patch, &'static str>
+{
+	use HunkLine::*;
+
+	let old_name = parse_file_name(lines.next(), "--- ").ok_or("Expected '---' header")?;
+	let new_name = parse_file_name(lines.next(), "+++ ").ok_or("Expected '+++' header")?;
+	let mut hunks = vec![];
+	while let Some(header) = lines.next_if(|line| line.starts_with("@@ ")) {
+		let ((old_start, mut old_len), (new_start, mut new_len)) = parse_hunk_header(header)?;
+		let mut hunk_lines = vec![];
+		while old_len + new_len > 0 {
+			let line = lines.next().ok_or("Unexpected end of hunk")?;
+			let kind = line.chars().next();
+			let text = line.get(1..).unwrap_or("").into();
+			let (hunk_line, old_count, new_count) = match kind {
+				// Some tools strip the trailing space from empty context lines
+				Some(' ') | None => (Context(text), 1, 1),
+				Some('-') => (Delete(text), 1, 0),
+				Some('+') => (Insert(text), 0, 1),
+				Some('\\') => continue, // "\ No newline at end of file"
+				_ => return Err("Invalid hunk line"),
+			};
+			if old_len < old_count || new_len < new_count {
+				return Err("Hunk is longer than its header")
+			}
+
+			old_len -= old_count;
+			new_len -= new_count;
+			hunk_lines.push(hunk_line);
+		}
+		lines.next_if(|line| line.starts_with('\\'));
+		hunks.push(Hunk { old_start, new_start, lines: hunk_lines });
+	}
+	Ok(Patch { old_name, new_name, hunks })
+}
+
+fn skip_to_file_header<'a, I: Iterator<Item=&'a str>>(lines: &mut Peekable<I>) {
+	while lines.next_if(|line| !line.starts_with("--- ")).is_some() {}
+}
+
+// Parses the unified diff of the first file in `text`.
+// Lines before its "---" header, such as "diff" or "index" lines,
+// and lines after its last hunk are ignored.
+pub fn parse_patch(text: &str) -> Result<Patch, &'static str> {
+	let mut lines = text.lines().peekable();
+	skip_to_file_header(&mut lines);
+	parse_file(&mut lines)
+}
+
+// Parses the unified diffs of every file in `text`, e.g. from git format-patch
+pub fn parse_patches(text: &str) -> Result<Vec<Patch>, &'static str> {
+	let mut lines = text.lines().peekable();
+	let mut patches = vec![];
+	loop {
+		skip_to_file_header(&mut lines);
+		if lines.peek().is_none() { return Ok(patches) }
+
+		patches.push(parse_file(&mut lines)?);
+	}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HunkResult {
+	// `line` is where the hunk starts in the new file, counting from 1.
+	// `offset` is how many lines it moved from where its header said,
+	// and `fuzz` is how many lines of context had to be ignored at each end.
+	Applied { line: usize, offset: isize, fuzz: usize },
+	// `line` is where the hunk was expected in the old file
+	Rejected { line: usize },
+}
+
+// Finds the position of `pattern` in a[min_index..] closest to `expected`,
+// trying later positions first at each distance, like patch(1)
+fn find_lines<S: AsRef<str>>(a: &[S], pattern: &[&str], min_index: usize, expected: usize)
+	-> Option<usize>
+{
+	let max_index = a.len().checked_sub(pattern.len())?;
+	if min_index > max_index { return None }
+
+	let matches = |index: usize| {
+		a[index..index + pattern.len()].iter().zip(pattern).all(|(line, &expected)| {
+			line.as_ref() == expected
+		})
+	};
+	let max_distance = expected.abs_diff(min_index).max(expected.abs_diff(max_index));
+	(0..=max_distance).find_map(|distance| {
+		let later = expected.checked_add(distance).filter(|&index| {
+			min_index <= index && index <= max_index && matches(index)
+		});
+		let earlier = expected.checked_sub(distance).filter(|&index| {
+			distance > 0 && min_index <= index && index <= max_index && matches(index)
+		});
+		later.or(earlier)
+	})
+}
+
+// Applies the hunks of `patch` to the lines `a`, searching for each hunk's
+// lines near where the previous hunk was found and ignoring up to `max_fuzz`
+// context lines at each end of a hunk if needed. Hunks that cannot be found
+// are skipped, leaving those lines unchanged.
+pub fn apply_unified<S: AsRef<str>>(a: &[S], patch: &Patch, max_fuzz: usize)
+	-> (Vec<String>, Vec<HunkResult>)
+{
+	use HunkLine::*;
+
+	let mut result = vec![];
+	let mut results = vec![];
+	let (mut a_index, mut offset) = (0, 0);
+	for hunk in &patch.hunks {
+		let old_lines: Vec<_> = hunk.old_lines().collect();
+		let (leading, trailing) = hunk.context_lens();
+		let expected = hunk.old_index() as isize + offset;
+		// Past the longest context, more fuzz would not ignore anything more
+		let found = (0..=max_fuzz.min(leading.max(trailing))).find_map(|fuzz| {
+			let (skip_front, skip_back) = (fuzz.min(leading), fuzz.min(trailing));
+			let pattern = &old_lines[skip_front..old_lines.len() - skip_back];
+			let expected = (expected + skip_front as isize).max(0) as usize;
+			let index = find_lines(a, pattern, a_index, expected)?;
+			Some((index, fuzz, skip_front, skip_back))
+		});
+		let Some((mut index, fuzz, skip_front, skip_back)) = found else {
+			let line = (hunk.old_start as isize + offset).max(0) as usize;
+			results.push(HunkResult::Rejected { line });
+			continue
+		};
+
+		offset = index as isize - skip_front as isize - hunk.old_index() as isize;
+		result.extend(a[a_index..index].iter().map(|line| line.as_ref().to_string()));
+		for line in &hunk.lines[skip_front..hunk.lines.len() - skip_back] {
+			match line {
+				Context(_) => {
+					result.push(a[index].as_ref().to_string());
+					index += 1;
+				},
+				Delete(_) => index += 1,
+				Insert(line) => result.push(line.to_string()),
+			}
+		}
+		a_index = index;
+		let line = (hunk.new_start as isize + offset).max(0) as usize;
+		results.push(HunkResult::Applied { line, offset, fuzz });
+	}
+	result.extend(a[a_index..].iter().map(|line| line.as_ref().to_string()));
+	(result, results)
+}
+
+// Describes hunks that did not apply cleanly, in the words of patch(1)
+pub fn report(results: &[HunkResult]) -> String {
+	use HunkResult::*;
+
+	let mut text = String::new();
+	let mut failed = 0;
+	for (i, result) in results.iter().enumerate() {
+		let hunk = i + 1;
+		match *result {
+			Applied { offset: 0, fuzz: 0, .. } => {},
+			Applied { line, offset, fuzz } => {
+				write!(text, "Hunk #{} succeeded at {}", hunk, line).unwrap();
+				if fuzz > 0 { write!(text, " with fuzz {}", fuzz).unwrap() }
+				if offset != 0 {
+					let plural = if offset == 1 { "" } else { "s" };
+					write!(text, " (offset {} line{})", offset, plural).unwrap();
+				}
+				text.push_str(".\n");
+			},
+			Rejected { line } => {
+				failed += 1;
+				writeln!(text, "Hunk #{} FAILED at {}.", hunk, line).unwrap();
+			},
+		}
+	}
+	if failed > 0 {
+		let plural = if results.len() == 1 { "" } else { "s" };
+		writeln!(text, "{} out of {} hunk{} FAILED", failed, results.len(), plural).unwrap();
+	}
+	text
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{diff, unified_diff};
+
+	fn numbered(range: std::ops::RangeInclusive<usize>) -> Vec<String> {
+		range.map(|i| format!("line {}", i)).collect()
+	}
+
+	fn make_patch(a: &[String], b: &[String], context: usize) -> Patch {
+		parse_patch(&unified_diff(a, &diff(a, b), context, "a.txt", "b.txt")).unwrap()
+	}
+
+	#[test]
+	fn test_parse() {
+		let text = "\
+diff --git a/x b/x
+index 123..456 100644
+--- a/x\t2024-01-01 00:00:00
++++ b/x
+@@ -1,3 +1,3 @@ fn main() {
+ a
+-b
++c
+
+@@ -10,0 +11 @@
++d
+\\ No newline at end of file
+";
+		assert_eq!(parse_patch(text), Ok(Patch {
+			old_name: "a/x".into(),
+			new_name: "b/x".into(),
+			hunks: vec![
+				Hunk {
+					old_start: 1,
+					new_start: 1,
+					lines: vec![
+						HunkLine::Context("a".into()),
+						HunkLine::Delete("b".into()),
+						HunkLine::Insert("c".into()),
+						HunkLine::Context("".into()),
+					],
+				},
+				Hunk { old_start: 10, new_start: 11, lines: vec![HunkLine::Insert("d".into())] },
+			],
+		}));
+		assert_eq!(parse_patch(""), Err("Expected '---' header"));
+		assert_eq!(parse_patch("--- a\n@@ -1 +1 @@"), Err("Expected '+++' header"));
+		assert_eq!(parse_patch("--- a\n+++ b\n@@ -1 +1\n"), Err("Invalid hunk header"));
+		assert_eq!(parse_patch("--- a\n+++ b\n@@ -x +1 @@\n"), Err("Invalid hunk range"));
+		assert_eq!(
+			parse_patch("--- a\n+++ b\n@@ -1,2 +1 @@\n-a\n"),
+			Err("Unexpected end of hunk"),
+		);
+		assert_eq!(parse_patch("--- a\n+++ b\n@@ -1 +1 @@\n*a\n"), Err("Invalid hunk line"));
+		assert_eq!(
+			parse_patch("--- a\n+++ b\n@@ -1 +1 @@\n\u{e9}t\u{e9}\n"),
+			Err("Invalid hunk line"),
+		);
+		assert_eq!(
+			parse_patch("--- a\n+++ b\n@@ -1 +1 @@\n\u{feff}-a\n"),
+			Err("Invalid hunk line"),
+		);
+		assert_eq!(
+			parse_patch("--- a\n+++ b\n@@ -1 +1 @@\n-a\n-b\n"),
+			Err("Hunk is longer than its header"),
+		);
+	}
+
+	#[test]
+	fn test_multiple_files() {
+		let text = "\
+From 0123abcd Mon Sep 17 00:00:00 2001
+Subject: [PATCH] Change two files
+
+---
+ x | 2 +-
+ y | 1 +
+ 2 files changed, 2 insertions(+), 1 deletion(-)
+
+diff --git a/x b/x
+index 123..456 100644
+--- a/x
++++ b/x
+@@ -1 +1 @@
+-a
++b
+diff --git a/y b/y
+--- a/y
++++ b/y
+@@ -1,0 +2 @@
++--- not a header
+-- 
+2.40.0
+
+";
+		let patches = parse_patches(text).unwrap();
+		assert_eq!(patches.len(), 2);
+		assert_eq!(&*patches[0].new_name, "b/x");
+		assert_eq!(
+			patches[0].hunks[0].lines,
+			[HunkLine::Delete("a".into()), HunkLine::Insert("b".into())],
+		);
+		assert_eq!(&*patches[1].old_name, "a/y");
+		assert_eq!(patches[1].hunks[0].lines, [HunkLine::Insert("--- not a header".into())]);
+		assert_eq!(parse_patch(text), Ok(patches[0].clone()));
+		assert_eq!(parse_patches(""), Ok(vec![]));
+		assert_eq!(
+			parse_patches("--- a\n+++ b\n@@ -1 +1 @@\n a\n--- c\n"),
+			Err("Expected '+++' header"),
+		);
+	}
+
+	#[test]
+	fn test_round_trip() {
+		let a = numbered(1..=30);
+		let mut b = a.clone();
+		b.remove(20);
+		b[10] = "changed".to_string();
+		b.insert(2, "new".to_string());
+		b.push("end".to_string());
+		for context in 0..4 {
+			let patch = make_patch(&a, &b, context);
+			assert_eq!(&*patch.old_name, "a.txt");
+			let (result, results) = apply_unified(&a, &patch, 0);
+			assert_eq!(result, b);
+			assert!(results.iter().all(|result| {
+				matches!(result, HunkResult::Applied { offset: 0, fuzz: 0, .. })
+			}));
+			assert_eq!(report(&results), "");
+		}
+		let patch = make_patch(&[], &b, 3);
+		assert_eq!(apply_unified(&Vec::<String>::new(), &patch, 0).0, b);
+	}
+
+	#[test]
+	fn test_offset() {
+		let a = numbered(1..=30);
+		let mut b = a.clone();
+		b[5] = "first".to_string();
+		b[20] = "second".to_string();
+		let patch = make_patch(&a, &b, 3);
+
+		// The target gained 3 lines at the start and lost 1 in the middle
+		let mut shifted = numbered(1..=30);
+		shifted.remove(12);
+		for i in 0..3 { shifted.insert(0, format!("extra {}", i)) }
+		let mut expected = shifted.clone();
+		expected[8] = "first".to_string();
+		expected[22] = "second".to_string();
+		let (result, results) = apply_unified(&shifted, &patch, 0);
+		assert_eq!(result, expected);
+		assert_eq!(results, [
+			HunkResult::Applied { line: 6, offset: 3, fuzz: 0 },
+			HunkResult::Applied { line: 20, offset: 2, fuzz: 0 },
+		]);
+		assert_eq!(report(&results), "\
+Hunk #1 succeeded at 6 (offset 3 lines).
+Hunk #2 succeeded at 20 (offset 2 lines).
+");
+	}
+
+	#[test]
+	fn test_fuzz() {
+		let a = numbered(1..=30);
+		let mut b = a.clone();
+		b[10] = "changed".to_string();
+		b[25] = "also changed".to_string();
+		let patch = make_patch(&a, &b, 3);
+
+		// The first hunk's outermost context lines differ, and the second's lines are gone
+		let mut target = a.clone();
+		target[7] = "edited".to_string();
+		target[13] = "edited".to_string();
+		target[25] = "removed".to_string();
+		let (result, results) = apply_unified(&target, &patch, 0);
+		assert_eq!(result, target);
+		assert_eq!(
+			results,
+			[HunkResult::Rejected { line: 8 }, HunkResult::Rejected { line: 23 }],
+		);
+
+		// Huge fuzz stops at the hunk's context
+		assert_eq!(
+			apply_unified(&target, &patch, usize::MAX),
+			apply_unified(&target, &patch, 3),
+		);
+		let (result, results) = apply_unified(&target, &patch, 2);
+		let mut expected = target.clone();
+		expected[10] = "changed".to_string();
+		assert_eq!(result, expected);
+		assert_eq!(results, [
+			HunkResult::Applied { line: 8, offset: 0, fuzz: 1 },
+			HunkResult::Rejected { line: 23 },
+		]);
+		assert_eq!(report(&results), "\
+Hunk #1 succeeded at 8 with fuzz 1.
+Hunk #2 FAILED at 23.
+1 out of 2 hunks FAILED
+");
+	}
+}
@@ -0,0 +1,208 @@
+use std::fmt::{Display, Write};
+use super::DiffElement;
+
+// A Change located in both sequences
+struct LocatedChange<'b, T> {
+	a_start: usize,
+	deletions: usize,
+	b_start: usize,
+	insertions: &'b [T],
+}
+
+impl<T> LocatedChange<'_, T> {
+	fn a_end(&self) -> usize {
+		self.a_start + self.deletions
+	}
+	fn b_end(&self) -> usize {
+		self.b_start + self.insertions.len()
+	}
+}
+
+fn locate_changes<'b, T>(diff: &[DiffElement<'b, T>]) -> Vec<LocatedChange<'b, T>> {
+	use DiffElement::*;
+
+	let (mut a_index, mut b_index) = (0, 0);
+	let mut changes = vec![];
+	for element in diff {
+		match *element {
+			Same(count) => {
+				a_index += count;
+				b_index += count;
+			},
+			Change(deletions, insertions) => {
+				changes.push(LocatedChange {
+					a_start: a_index,
+					deletions,
+					b_start: b_index,
+					insertions,
+				});
+				a_index += deletions;
+				b_index += insertions.len();
+			},
+		}
+	}
+	changes
+}
+
+// Formats a hunk range as "line,count", where line counts from 1,
+// or is the line before the range if it is empty. ",1" is omitted.
+fn write_range(text: &mut String, start: usize, len: usize) {
+	match len {
+		0 => write!(text, "{},0", start),
+		1 => write!(text, "{}", start + 1),
+		_ => write!(text, "{},{}", start + 1, len),
+	}.unwrap()
+}
+
+fn write_lines<T: Display>(text: &mut String, prefix: char, lines: &[T]) {
+	for line in lines { writeln!(text, "{}{}", prefix, line).unwrap() }
+}
+
+// Renders a diff of `a` as unified diff text, showing `context` unchanged lines
+// around each change. As in GNU diff, changes separated by at most 2 * context
+// unchanged lines share a hunk. Returns an empty string if nothing changed.
+pub fn unified_diff<T: Display>(
+	a: &[T],
+	diff: &[DiffElement<T>],
+	context: usize,
+	a_name: &str,
+	b_name: &str,
+) -> String {
+	let changes = locate_changes(diff);
+	let mut text = String::new();
+	if changes.is_empty() { return text }
+
+	writeln!(text, "--- {}\n+++ {}", a_name, b_name).unwrap();
+	let mut hunk_start = 0;
+	while hunk_start < changes.len() {
+		let mut hunk_end = hunk_start + 1;
+		while hunk_end < changes.len() &&
+			changes[hunk_end].a_start - changes[hunk_end - 1].a_end() <= 2 * context {
+			hunk_end += 1;
+		}
+		let hunk = &changes[hunk_start..hunk_end];
+		let (first, last) = (&hunk[0], &hunk[hunk.len() - 1]);
+		let leading = first.a_start.min(context);
+		let trailing = (a.len() - last.a_end()).min(context);
+		let a_start = first.a_start - leading;
+		let a_end = last.a_end() + trailing;
+		let b_start = first.b_start - leading;
+		let b_end = last.b_end() + trailing;
+
+		text.push_str("@@ -");
+		write_range(&mut text, a_start, a_end - a_start);
+		text.push_str(" +");
+		write_range(&mut text, b_start, b_end - b_start);
+		text.push_str(" @@\n");
+		let mut a_index = a_start;
+		for change in hunk {
+			write_lines(&mut text, ' ', &a[a_index..change.a_start]);
+			write_lines(&mut text, '-', &a[change.a_start..change.a_end()]);
+			write_lines(&mut text, '+', change.insertions);
+			a_index = change.a_end();
+		}
+		write_lines(&mut text, ' ', &a[a_index..a_end]);
+		hunk_start = hunk_end;
+	}
+	text
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::diff;
+
+	fn render(a: &[&str], b: &[&str], context: usize) -> String {
+		unified_diff(a, &diff(a, b), context, "old", "new")
+	}
+
+	// Expected outputs are from GNU diff -U<context>
+	#[test]
+	fn test_hunks() {
+		let a = ["a", "b", "c", "d", "f", "g", "h", "j", "q", "z"];
+		let b = ["a", "b", "c", "d", "e", "f", "g", "i", "j", "k", "r", "x", "y", "z"];
+		assert_eq!(render(&a, &b, 1), "\
+--- old
++++ new
+@@ -4,7 +4,11 @@
+ d
++e
+ f
+ g
+-h
++i
+ j
+-q
++k
++r
++x
++y
+ z
+");
+		assert_eq!(render(&a, &b, 0), "\
+--- old
++++ new
+@@ -4,0 +5 @@
++e
+@@ -7 +8 @@
+-h
++i
+@@ -9 +10,4 @@
+-q
++k
++r
++x
++y
+");
+	}
+
+	#[test]
+	fn test_merging() {
+		let a: Vec<_> = (1..=20).map(|i| i.to_string()).collect();
+		let mut b = a.clone();
+		b[3] = "X".to_string();
+		b[10] = "Y".to_string();
+		b[18] = "Z".to_string();
+		// 6 unchanged lines between the first changes merge them; 7 do not
+		let expected = "\
+--- a
++++ b
+@@ -1,14 +1,14 @@
+ 1
+ 2
+ 3
+-4
++X
+ 5
+ 6
+ 7
+ 8
+ 9
+ 10
+-11
++Y
+ 12
+ 13
+ 14
+@@ -16,5 +16,5 @@
+ 16
+ 17
+ 18
+-19
++Z
+ 20
+";
+		assert_eq!(unified_diff(&a, &diff(&a, &b), 3, "a", "b"), expected);
+	}
+
+	#[test]
+	fn test_edges() {
+		assert_eq!(render(&["a", "b"], &["a", "b"], 3), "");
+		assert_eq!(render(&[], &["x", "y"], 3), "--- old\n+++ new\n@@ -0,0 +1,2 @@\n+x\n+y\n");
+		assert_eq!(render(&["x"], &[], 3), "--- old\n+++ new\n@@ -1 +0,0 @@\n-x\n");
+		assert_eq!(
+			render(&["a", "b", "c"], &["a", "c"], 5),
+			"--- old\n+++ new\n@@ -1,3 +1,2 @@\n a\n-b\n c\n",
+		);
+	}
+}
@@ -7,7 +7,7 @@ use bit_vector::BitVector;
 
 fn add_bit(bits: &BitVector, bit: bool) -> BitVector {
 	let mut new_bits = BitVector::with_capacity(bits.len() + 1);
-	new_bits.extend(bits);
+	new_bits.extend_from_bitvector(bits);
 	new_bits.push(bit);
 	new_bits
 }
@@ -107,12 +107,12 @@ impl<'a, T: 'a + Hash + Eq + Clone> HuffmanEncoding<T> {
 	}
 	pub fn encode<V: IntoIterator<Item=T>>(&self, values: V) -> BitVector {
 		let mut bits = BitVector::new();
-		for c in values { bits.extend(&self.encodings[&c]) }
+		for c in values { bits.extend_from_bitvector(&self.encodings[&c]) }
 		bits
 	}
 	pub fn encode_ref<V: IntoIterator<Item=&'a T>>(&self, values: V) -> BitVector {
 		let mut bits = BitVector::new();
-		for c in values { bits.extend(&self.encodings[c]) }
+		for c in values { bits.extend_from_bitvector(&self.encodings[c]) }
 		bits
 	}
 